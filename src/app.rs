@@ -1,29 +1,323 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use anyhow::{anyhow, bail, ensure, Context};
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Weekday};
+
+use comparison::Dataset;
+use display::DisplaySettings;
+use range::DateRange;
+use rates::RateSchedule;
+use raw_input::{FailedInput, Target};
+use shortcuts::Action;
+
+mod bookmarks;
+mod budget;
+mod cache;
+mod calendar;
+mod comparison;
+mod cost_rate;
+mod csv_export;
+mod cycle;
+mod day_view;
+mod density;
+mod display;
+mod emissions;
+mod gauge;
+mod green_button;
+mod heatmap;
+mod migration;
+mod overview;
+mod profile;
+mod range;
+mod raster;
+mod rates;
+mod raw_input;
+mod report;
+mod search;
+mod shortcuts;
+mod signatures;
+mod svg;
+mod table;
+mod tags;
+mod timeline;
+mod timestamp_csv;
+mod trend;
+mod weekday;
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct TemplateApp {
+    /// Layout version of the saved state, see [`migration`]. Missing from state saved before it
+    /// existed, which is version 0.
+    #[serde(default)]
+    version: u32,
+
     error: Option<String>,
+    /// Show the causes behind `error` under it, like the unknown unit behind "On line 42".
+    show_error_causes: bool,
 
     data: Option<UsageData>,
+    /// The other meters in the file `data` came from, when it interleaved several.
+    other_meters: Vec<UsageData>,
+    /// Other files drawn over `data` in the day view.
+    comparisons: Vec<Dataset>,
 
     year: u16,
     month: u8,
     day: u8,
+
+    day_filter: DayFilter,
+    range: DateRange,
+
+    display: DisplaySettings,
+
+    view: View,
+    /// Which weekday [`View::Weekday`] compares.
+    compared_weekday: Weekday,
+    on_open: OnOpen,
+
+    rates: RateSchedule,
+    /// A plan to price the same usage under, for weighing up a switch.
+    alternative_rates: RateSchedule,
+    /// How many of the most recent days the cost projection averages over.
+    projection_days: usize,
+    /// The billing cycle the bill breakdown is for, by its first day. `None` for the latest.
+    #[serde(skip)]
+    breakdown_cycle: Option<NaiveDate>,
+    /// Data whose latest reading is more than this many days old is flagged as stale.
+    stale_after_days: u32,
+    /// How many of the data's most recent days "Trim now" keeps.
+    retention_days: u32,
+    /// "Trim now" was clicked and is waiting for the discard to be confirmed.
+    #[serde(skip)]
+    confirm_trim: bool,
+    /// Leave an incomplete last day out of averages, where it would read as a low-usage day.
+    exclude_partial_day: bool,
+    /// Skip rows that fail to parse instead of rejecting the whole file, read rows missing
+    /// their note column as having no note, and ignore a trailing comma after the note.
+    lenient_parsing: bool,
+    /// See [`ParseOptions::skip_totals_rows`].
+    skip_totals_rows: bool,
+    /// Run [`UsageData::normalize`] on every file parsed.
+    normalize_on_load: bool,
+    /// See [`UsageData::snap_hour_buckets`].
+    snap_hour_buckets: bool,
+    csv_format: csv_export::CsvFormat,
+    /// kWh per billing cycle to track usage against.
+    budget: Option<f64>,
+    /// kg CO₂ per kWh, for estimating emissions alongside cost.
+    emissions_factor: f64,
+    /// Hourly grid intensity to estimate emissions with instead of the flat factor.
+    grid_intensity: Option<emissions::GridIntensity>,
+    /// Each day's emissions, kept until the data, the factor, or the intensity file changes.
+    #[serde(skip)]
+    daily_emissions: Option<emissions::DailyEmissions>,
+    signature_settings: signatures::SignatureSettings,
+    /// What the last scan for recurring loads found, if one was run on this data.
+    #[serde(skip)]
+    signatures: Option<Vec<signatures::Signature>>,
+    trend_settings: trend::TrendSettings,
+    bookmarks: bookmarks::Bookmarks,
+    /// Seconds between saves of the app state while it runs, on top of the save on exit.
+    autosave_seconds: u64,
+
+    /// Where `data` was loaded from, for rebuilding its cache.
+    #[serde(skip)]
+    data_path: Option<std::path::PathBuf>,
+    #[serde(skip)]
+    summary: Option<DatasetSummary>,
+    #[serde(skip)]
+    entry_costs: Option<rates::EntryCosts>,
+    #[serde(skip)]
+    trend: Option<trend::Trend>,
+    #[serde(skip)]
+    show_shortcuts: bool,
+    /// The text of a file that failed to parse, kept so the bad line can be shown.
+    #[serde(skip)]
+    failed_input: Option<FailedInput>,
+    #[serde(skip)]
+    show_raw_input: bool,
+    #[serde(skip)]
+    load_stats: Option<LoadStats>,
+    #[serde(skip)]
+    note_query: String,
+    #[serde(skip)]
+    usage_bounds: search::UsageBounds,
+    #[serde(skip)]
+    new_holiday: NaiveDate,
+    #[serde(skip)]
+    date_input: String,
+    #[serde(skip)]
+    date_input_invalid: bool,
+    #[serde(skip)]
+    new_bookmark: String,
+    /// What led to `error`, outermost first.
+    #[serde(skip)]
+    error_causes: Vec<String>,
 }
 
-#[derive(serde::Deserialize, serde::Serialize)]
+/// How the most recent file load went, for reasoning about performance.
+struct LoadStats {
+    entries: usize,
+    duration: std::time::Duration,
+    skipped: Vec<SkippedLine>,
+    from_cache: bool,
+    normalized: Normalized,
+}
+
+/// What [`UsageData::normalize`] had to fix.
+#[derive(Default)]
+struct Normalized {
+    /// Whether the entries were out of chronological order.
+    reordered: bool,
+    /// How many entries were exact copies of the one before them once sorted.
+    duplicates_removed: usize,
+}
+
+/// Whole-dataset figures, computed once per load rather than every frame.
+struct DatasetSummary {
+    total: f64,
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    /// The [`RateSchedule::cycle_start_day`] `cycle_totals` was split on.
+    cycle_start_day: u32,
+    cycle_totals: BTreeMap<NaiveDate, f64>,
+    /// Unfiltered total for every day with data.
+    daily_totals: BTreeMap<NaiveDate, f64>,
+    interval: Option<TimeDelta>,
+    partial_day: Option<NaiveDate>,
+    /// In kW, weighted by interval length.
+    average_power: Option<f64>,
+    /// When the last interval in the file starts.
+    latest_reading: NaiveDateTime,
+}
+
+impl DatasetSummary {
+    fn new(data: &UsageData, rates: &RateSchedule) -> Option<Self> {
+        Some(Self {
+            total: data.total_energy(),
+            average_power: data.average_power(),
+            first_day: data.entries.iter().map(UsageEntry::day).min()?,
+            last_day: data.entries.iter().map(UsageEntry::day).max()?,
+            latest_reading: data.entries.iter().map(UsageEntry::start).max()?,
+            cycle_start_day: rates.cycle_start_day,
+            cycle_totals: rates.cycle_totals(data),
+            daily_totals: data.daily_totals(&DayFilter::default()),
+            interval: data.detected_interval(),
+            partial_day: data.partial_day(),
+        })
+    }
+
+    /// `daily_totals` without the partial day, if `exclude` is set.
+    fn complete_days(
+        &self,
+        daily_totals: &BTreeMap<NaiveDate, f64>,
+        exclude: bool,
+    ) -> BTreeMap<NaiveDate, f64> {
+        let mut daily_totals = daily_totals.clone();
+        if let (true, Some(day)) = (exclude, self.partial_day) {
+            daily_totals.remove(&day);
+        }
+        daily_totals
+    }
+
+    fn cost(&self, rates: &RateSchedule) -> f64 {
+        self.cycle_totals
+            .values()
+            .map(|&total| rates.cycle_cost(total))
+            .sum()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+enum View {
+    /// Hourly bars for the selected day.
+    #[default]
+    Day,
+    /// A small chart of daily totals for every month.
+    Months,
+    /// One weekday's hourly profile for each week in the range.
+    Weekday,
+    /// The selected day's month as a calendar shaded by daily totals.
+    Calendar,
+    /// Average usage by weekday and hour.
+    WeekHours,
+    /// Usage over the range in bars anywhere from an hour to a week wide.
+    Trend,
+    /// The selected day's cost per hour.
+    CostRate,
+    /// How many entries each day has, for spotting sparse data.
+    EntryCounts,
+    /// Each day of the range's usage less its exports.
+    DailyNet,
+    /// Every entry in the range on a continuous time axis.
+    Timeline,
+    /// Each day's estimated emissions across the range.
+    Emissions,
+    /// Usage adding up over the selected day's billing cycle, against the tier thresholds.
+    Cycle,
+    /// One row per day in the range, with its total and hourly profile.
+    Days,
+}
+
+/// Which day is shown after opening a file.
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+enum OnOpen {
+    #[default]
+    JumpToLatest,
+    /// Stay on the last viewed day, as long as the new file covers it.
+    RememberLastViewed,
+}
+
+/// Which days are counted towards totals, averages, and the typical-day profile.
+#[derive(Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct DayFilter {
+    exclude_weekends: bool,
+    exclude_holidays: bool,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl DayFilter {
+    fn includes(&self, date: NaiveDate) -> bool {
+        if self.exclude_weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            return false;
+        }
+        !(self.exclude_holidays && self.holidays.contains(&date))
+    }
+}
+
+#[derive(PartialEq, serde::Deserialize, serde::Serialize)]
 struct UsageEntry {
     date: (u16, u8, u8),
     interval_start_hour: u8,
+    #[serde(default)]
+    interval_start_minute: u8,
     interval_end_hour: u8,
+    #[serde(default)]
+    interval_end_minute: u8,
     kilowatt_hours: f64,
-    note: String,
+    /// `None` for the usual empty note, so most entries don't allocate one.
+    #[serde(deserialize_with = "deserialize_note")]
+    note: Option<Box<str>>,
+    /// The hour this entry counts towards in hourly views, when that isn't the hour it starts
+    /// in. See [`UsageData::snap_hour_buckets`].
+    #[serde(default)]
+    hour_bucket: Option<u8>,
+    /// Marked as a bad reading, so it's left out of totals, averages, and cost but kept.
+    #[serde(default)]
+    excluded: bool,
+    /// Index into [`UsageData::meters`] of the meter this reading is from.
+    #[serde(default)]
+    meter: u8,
 }
 
 impl UsageEntry {
-    fn parse(line: &str) -> anyhow::Result<Self> {
+    /// Parses one row of the portal's export. With `lenient`, a row that ends right after its
+    /// units, without even the comma before an empty note, is read as having no note, and a
+    /// single empty field after the note is ignored.
+    fn parse(line: &str, lenient: bool) -> anyhow::Result<Self> {
         let mut iterator = line.split(',');
 
         let entry_type = iterator.next();
@@ -52,12 +346,18 @@ impl UsageEntry {
                 .ok_or_else(|| anyhow!("Expected Year"))?
                 .parse()?;
 
+            ensure!(
+                NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).is_some(),
+                "Invalid date {date_str:?}"
+            );
+
             date = (year, month, day);
         } else {
             bail!("Insufficient entries in line, expected date.")
         }
 
         let interval_start_hour;
+        let interval_start_minute;
         if let Some(interval_start_str) = iterator.next() {
             let Some((hour, rest)) = interval_start_str.split_once(':') else {
                 bail!("Invalid date {interval_start_str:?}")
@@ -67,12 +367,18 @@ impl UsageEntry {
                 bail!("Invalid date {interval_start_str:?}")
             };
 
+            let minutes = minutes.parse::<u8>()?;
+            ensure!(minutes < 60, "Invalid minutes in {interval_start_str:?}");
+            interval_start_minute = minutes;
+
+            // A 12-hour clock runs 12, 1, ..., 11, so a single digit needs no leading zero and
+            // taking the hour modulo 12 turns 12 AM into 0 and 12 PM into 12.
+            let hour = hour.parse::<u8>()?;
             ensure!(
-                minutes == "00",
-                "Program is illequiped to handle non-zero minutes"
+                (1..=12).contains(&hour),
+                "Invalid hour in {interval_start_str:?}"
             );
-
-            let hour = hour.parse::<u8>()? % 12;
+            let hour = hour % 12;
             interval_start_hour = hour
                 + match hemiday {
                     "AM" | "am" => 0,
@@ -84,6 +390,7 @@ impl UsageEntry {
         }
 
         let interval_end_hour;
+        let interval_end_minute;
         if let Some(interval_end_str) = iterator.next() {
             let Some((hour, rest)) = interval_end_str.split_once(':') else {
                 bail!("Invalid date {interval_end_str:?}")
@@ -93,12 +400,16 @@ impl UsageEntry {
                 bail!("Invalid date {interval_end_str:?}")
             };
 
+            let minutes = minutes.parse::<u8>()?;
+            ensure!(minutes < 60, "Invalid minutes in {interval_end_str:?}");
+            interval_end_minute = minutes;
+
+            let hour = hour.parse::<u8>()?;
             ensure!(
-                minutes == "00",
-                "Program is illequiped to handle non-zero minutes"
+                (1..=12).contains(&hour),
+                "Invalid hour in {interval_end_str:?}"
             );
-
-            let hour = hour.parse::<u8>()? % 12;
+            let hour = hour % 12;
             interval_end_hour = hour
                 + match hemiday {
                     "AM" | "am" => 0,
@@ -108,198 +419,2596 @@ impl UsageEntry {
         } else {
             bail!("Insuffiecient entries in line, expected end time");
         }
-
-        let kilowatt_hours;
-        if let (Some(scalar), Some(unit)) = (iterator.next(), iterator.next()) {
-            let scalar = scalar.parse::<f64>().unwrap();
-            kilowatt_hours = scalar
-                * match unit {
-                    "kWh" => 1.0,
-                    _ => bail!("Unknown unit {unit:?}"),
-                };
+        // `12:00 AM` is midnight at either end of the day. As an end time after a later start, it
+        // must be the end, so it's hour 24 rather than 0.
+        let ends_at_midnight = (interval_end_hour, interval_end_minute) == (0, 0)
+            && (interval_start_hour, interval_start_minute) != (0, 0);
+        let interval_end_hour = if ends_at_midnight {
+            24
         } else {
+            interval_end_hour
+        };
+
+        let Some(usage) = iterator.next() else {
             bail!("Insufficient entries in line, expected usage and unit");
-        }
+        };
+        // Some exports put the value and its unit in one column, like `0.42 kWh`, leaving out
+        // the UNITS column.
+        let (scalar, unit) = match usage.trim_matches('"').split_once(' ') {
+            Some((scalar, unit)) => (scalar, unit.trim()),
+            None => match iterator.next() {
+                Some(unit) => (usage, unit),
+                None => bail!("Insufficient entries in line, expected usage and unit"),
+            },
+        };
+        let scalar = scalar
+            .parse::<f64>()
+            .with_context(|| format!("Invalid usage {scalar:?}"))?;
+        let kilowatt_hours = scalar
+            * match unit {
+                "kWh" => 1.0,
+                _ => bail!("Unknown unit {unit:?}"),
+            };
 
-        let Some(note) = iterator.next().map(|v| v.to_owned()) else {
-            bail!("Expected final entry for note (may be empty, but comma is expected)")
+        let note = match iterator.next() {
+            Some(note) => UsageEntry::note_from(note),
+            None if lenient => None,
+            None => bail!("Expected final entry for note (may be empty, but comma is expected)"),
         };
 
-        ensure!(iterator.next().is_none(), "Extra entries in the line");
+        // Some exporters end every row with one more comma than the header has.
+        let extra = iterator.next();
+        let trailing_comma = lenient && extra == Some("") && iterator.next().is_none();
+        ensure!(
+            extra.is_none() || trailing_comma,
+            "Extra entries in the line"
+        );
 
         Ok(Self {
             date,
             interval_start_hour,
+            interval_start_minute,
             interval_end_hour,
+            interval_end_minute,
             kilowatt_hours,
             note,
+            hour_bucket: None,
+            excluded: false,
+            meter: 0,
+        })
+    }
+
+    /// Builds an entry without a note from when it started and how long it lasted, as the
+    /// formats that don't spell out the end time describe intervals.
+    fn from_interval(
+        start: NaiveDateTime,
+        duration: TimeDelta,
+        kilowatt_hours: f64,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            start.second() == 0 && duration > TimeDelta::zero() && duration.num_seconds() % 60 == 0,
+            "Program is illequiped to handle intervals that aren't whole minutes (at {start})"
+        );
+        let end_minutes = (start.hour() * 60 + start.minute()) as i64 + duration.num_minutes();
+        ensure!(
+            end_minutes <= 24 * 60,
+            "Interval starting at {start} runs past the end of the day"
+        );
+        Ok(Self {
+            date: (start.year() as u16, start.month() as u8, start.day() as u8),
+            interval_start_hour: start.hour() as u8,
+            interval_start_minute: start.minute() as u8,
+            interval_end_hour: (end_minutes / 60) as u8,
+            interval_end_minute: (end_minutes % 60) as u8,
+            kilowatt_hours,
+            note: None,
+            hour_bucket: None,
+            excluded: false,
+            meter: 0,
         })
     }
+
+    fn day(&self) -> NaiveDate {
+        let (year, month, day) = self.date;
+        NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).unwrap()
+    }
+
+    fn start(&self) -> NaiveDateTime {
+        self.day()
+            .and_hms_opt(
+                self.interval_start_hour as u32,
+                self.interval_start_minute as u32,
+                0,
+            )
+            .unwrap()
+    }
+
+    /// The hour this entry is counted towards in hourly views.
+    fn hour_bucket(&self) -> u8 {
+        self.hour_bucket.unwrap_or(self.interval_start_hour)
+    }
+
+    /// When the interval starts, in hours since midnight.
+    fn start_hours(&self) -> f64 {
+        self.interval_start_hour as f64 + self.interval_start_minute as f64 / 60.0
+    }
+
+    /// Length of the interval in hours.
+    fn duration_hours(&self) -> f64 {
+        let mut end = self.interval_end_hour as f64 + self.interval_end_minute as f64 / 60.0;
+        // Entries saved before midnight ends were parsed as hour 24 may still have it as hour 0.
+        if end <= self.start_hours() {
+            end += 24.0;
+        }
+        end - self.start_hours()
+    }
+
+    /// Stores `note`, keeping nothing for an empty one.
+    fn note_from(note: &str) -> Option<Box<str>> {
+        (!note.is_empty()).then(|| note.into())
+    }
+
+    fn note(&self) -> &str {
+        self.note.as_deref().unwrap_or_default()
+    }
+
+    /// Whether the utility marked this reading as estimated rather than metered.
+    fn is_estimated(&self) -> bool {
+        self.note().to_lowercase().contains("estimated")
+    }
+
+    /// Whether the note contains `query`, ignoring case. This is the one definition of a note
+    /// search match.
+    fn note_matches(&self, query: &str) -> bool {
+        self.note()
+            .to_lowercase()
+            .contains(&query.trim().to_lowercase())
+    }
+
+    /// The note split on `delimiter` into trimmed, non-empty tags. Nothing is split with an empty
+    /// delimiter, so a whole note is a single tag.
+    fn tags<'a>(&'a self, delimiter: &'a str) -> Vec<&'a str> {
+        let parts: Box<dyn Iterator<Item = &str>> = if delimiter.is_empty() {
+            Box::new(std::iter::once(self.note()))
+        } else {
+            Box::new(self.note().split(delimiter))
+        };
+        parts.map(str::trim).filter(|tag| !tag.is_empty()).collect()
+    }
+}
+
+/// Reads a saved note, which state from before notes were optional has as a bare string rather
+/// than `Some(..)` or `None`. An empty note is kept as no note either way.
+fn deserialize_note<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Box<str>>, D::Error> {
+    struct NoteVisitor;
+
+    impl<'de> serde::de::Visitor<'de> for NoteVisitor {
+        type Value = Option<Box<str>>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            formatter.write_str("a note, or an optional note")
+        }
+
+        fn visit_str<E: serde::de::Error>(self, note: &str) -> Result<Self::Value, E> {
+            Ok(UsageEntry::note_from(note))
+        }
+
+        fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: serde::Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_str(self)
+        }
+    }
+
+    // Only a self-describing format can say which of the two it holds. The cache isn't one, and
+    // has only ever held optional notes.
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(NoteVisitor)
+    } else {
+        deserializer.deserialize_option(NoteVisitor)
+    }
+}
+
+/// Error context naming the (1-based) line a parse error came from, so it can be pointed at.
+#[derive(Debug, Clone, Copy)]
+struct OnLine(usize);
+
+impl std::fmt::Display for OnLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "On line {}", self.0)
+    }
+}
+
+/// How forgiving a parse of the portal's export is.
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+struct ParseOptions {
+    /// Leave out rows that fail to parse, and accept rows missing their note, instead of failing
+    /// the whole file.
+    lenient: bool,
+    /// Leave out rows that are obviously a summary, like `Total,,,,123,kWh,`, with a warning.
+    skip_totals_rows: bool,
+}
+
+/// Whether `line` looks like a summary row some exports end with rather than a reading: its
+/// first column starts with "Total".
+fn is_totals_row(line: &str) -> bool {
+    line.split(',').next().is_some_and(|first| {
+        first
+            .trim()
+            .trim_matches('"')
+            .get(..5)
+            .is_some_and(|start| start.eq_ignore_ascii_case("total"))
+    })
+}
+
+/// A row a parse left out, as [`ParseOptions`] allow.
+struct SkippedLine {
+    /// 1-based, like [`OnLine`].
+    line: usize,
+    message: String,
+}
+
+/// How long ago `latest` was, flagged once it's more than `stale_after_days` old so it's clear
+/// a fresh download is due.
+fn freshness_ui(
+    ui: &mut egui::Ui,
+    latest: NaiveDateTime,
+    date_format: display::DateFormat,
+    time_format: display::TimeFormat,
+    stale_after_days: u32,
+) {
+    let days = (chrono::Local::now().date_naive() - latest.date()).num_days();
+    let ago = match days {
+        ..=0 => "today".to_owned(),
+        1 => "yesterday".to_owned(),
+        days => format!("{days} days ago"),
+    };
+    let text = format!(
+        "Latest reading: {} {} ({ago})",
+        date_format.format(latest.date()),
+        time_format.format(latest.hour() as u8, latest.minute() as u8)
+    );
+    if days > stale_after_days as i64 {
+        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {text}"))
+            .on_hover_text("The data may be out of date; download a fresh export");
+    } else {
+        ui.label(text);
+    }
+}
+
+/// The causes behind `error`, outermost first, not including its own message.
+fn error_causes(error: &anyhow::Error) -> Vec<String> {
+    error.chain().skip(1).map(ToString::to_string).collect()
+}
+
+/// Column headers of the utility portal's CSV export, expected on the third line.
+const HEADER: &str = "TYPE,DATE,START TIME,END TIME,USAGE,UNITS,NOTES";
+
+/// A few rows in the portal's format, for [`sample_file`]: an hour apiece, the last ending at
+/// midnight and one with a note.
+const SAMPLE_ROWS: &str = "\
+Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,
+Electric usage,1/15/2024,1:00 AM,2:00 AM,0.38,kWh,
+Electric usage,1/15/2024,7:00 AM,8:00 AM,1.25,kWh,Estimated
+Electric usage,1/15/2024,11:00 PM,12:00 AM,0.51,kWh,
+";
+
+/// A small file the portal parser accepts, showing the format by example: the address, a blank
+/// line, [`HEADER`], then one row per interval.
+fn sample_file() -> String {
+    format!("123 Example St, Springfield\n\n{HEADER}\n{SAMPLE_ROWS}")
+}
+
+/// Whether `line` is the portal's column headers, and if so whether they start with an extra
+/// column naming the meter each row is from, as exports covering several meters have.
+fn portal_header(line: &str) -> Option<bool> {
+    if line == HEADER {
+        Some(false)
+    } else {
+        line.split_once(',')
+            .filter(|(_, rest)| *rest == HEADER)
+            .map(|_| true)
+    }
+}
+
+/// Reads `bytes` as UTF-8, or failing that as Windows-1252, which some portals export in (a
+/// degree sign in an address is enough to break UTF-8).
+fn decode_text(bytes: &[u8]) -> anyhow::Result<std::borrow::Cow<'_, str>> {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(text.into());
+    }
+    encoding_rs::WINDOWS_1252
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .context("The file is neither UTF-8 nor Windows-1252 text")
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 struct UsageData {
     address: String,
+    /// Labels of the meters the entries are from, empty when the file doesn't say.
+    #[serde(default)]
+    meters: Vec<String>,
     entries: Vec<UsageEntry>,
 }
 
 impl UsageData {
-    fn parse(input: &str) -> anyhow::Result<Self> {
-        let mut lines = input.split("\n");
+    /// Parses the portal's CSV export. Rows `options` allows leaving out are reported instead.
+    fn parse(input: &str, options: ParseOptions) -> anyhow::Result<(Self, Vec<SkippedLine>)> {
+        Self::parse_reader(input.as_bytes(), options)
+    }
+
+    /// Strictly parses portal `rows`, under a made-up address and the usual headers.
+    #[cfg(test)]
+    fn from_rows(rows: &str) -> Self {
+        let options = ParseOptions {
+            lenient: false,
+            skip_totals_rows: false,
+        };
+        let input = format!("Test address\n\n{HEADER}\n{rows}");
+        Self::parse(&input, options).unwrap().0
+    }
 
-        let Some(address) = lines.next().map(|s| s.to_string()) else {
-            bail!("Expected an address at the top of the file")
+    /// [`Self::parse`], reading a line at a time so a multi-year file is never held in memory
+    /// alongside its entries.
+    fn parse_reader(
+        mut reader: impl std::io::BufRead,
+        options: ParseOptions,
+    ) -> anyhow::Result<(Self, Vec<SkippedLine>)> {
+        let ParseOptions {
+            lenient,
+            skip_totals_rows,
+        } = options;
+        // One pair of buffers is reused for every line rather than allocating each. Lines are
+        // decoded one at a time, so a stray non-UTF-8 byte only changes how its own line is read.
+        let mut buffer = String::new();
+        let mut bytes = Vec::new();
+        let mut next_line = |buffer: &mut String| -> anyhow::Result<bool> {
+            buffer.clear();
+            bytes.clear();
+            if reader
+                .read_until(b'\n', &mut bytes)
+                .context("Could not read the file")?
+                == 0
+            {
+                return Ok(false);
+            }
+            buffer.push_str(&decode_text(&bytes)?);
+            if buffer.ends_with('\n') {
+                buffer.pop();
+            }
+            Ok(true)
         };
 
-        ensure!(lines.next() == Some(""), "Expected second line to be blank");
         ensure!(
-            lines.next() == Some("TYPE,DATE,START TIME,END TIME,USAGE,UNITS,NOTES"),
-            "Incorrect headers. Format must have changed or something. Sorry"
+            next_line(&mut buffer)?,
+            "Expected an address at the top of the file"
+        );
+        ensure!(
+            portal_header(&buffer).is_none(),
+            "No address line found; is this a headers-only export?"
+        );
+        let address = buffer.clone();
+
+        ensure!(
+            next_line(&mut buffer)? && buffer.is_empty(),
+            "Expected second line to be blank"
         );
+        let has_meter_column = next_line(&mut buffer)?
+            .then(|| portal_header(&buffer))
+            .flatten()
+            .context("Incorrect headers. Format must have changed or something. Sorry")?;
 
-        let entries = lines
-            .filter(|s| !s.is_empty())
-            .enumerate()
-            .map(|(i, s)| UsageEntry::parse(s).with_context(|| format!("On line {}", i + 3)))
-            .collect::<Result<Vec<_>, _>>()?;
+        let mut meters = Vec::new();
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+        // Entries start on the fourth line.
+        let mut line = 3;
+        while next_line(&mut buffer)? {
+            line += 1;
+            if buffer.is_empty() {
+                continue;
+            }
+            if skip_totals_rows && is_totals_row(&buffer) {
+                skipped.push(SkippedLine {
+                    line,
+                    message: "Looks like a totals row rather than a reading".to_owned(),
+                });
+                continue;
+            }
+            let parsed = if has_meter_column {
+                Self::parse_metered(&buffer, &mut meters, lenient)
+            } else {
+                UsageEntry::parse(&buffer, lenient)
+            };
+            match parsed {
+                Ok(entry) => entries.push(entry),
+                Err(error) if lenient => skipped.push(SkippedLine {
+                    line,
+                    message: format!("{error:#}"),
+                }),
+                Err(error) => return Err(error.context(OnLine(line))),
+            }
+        }
+        // Growing by doubling can leave up to half the capacity unused.
+        entries.shrink_to_fit();
 
-        Ok(Self { address, entries })
+        Ok((
+            Self {
+                address,
+                meters,
+                entries,
+            },
+            skipped,
+        ))
     }
-}
 
-impl Default for TemplateApp {
-    fn default() -> Self {
-        Self {
-            error: None,
-            data: None,
-            year: 2025,
-            month: 06,
-            day: 21,
+    /// Parses a row that starts with the meter it's from, adding the meter to `meters` the first
+    /// time it's seen.
+    fn parse_metered(
+        line: &str,
+        meters: &mut Vec<String>,
+        lenient: bool,
+    ) -> anyhow::Result<UsageEntry> {
+        let (meter, rest) = line.split_once(',').context("Missing the meter column")?;
+        let meter = meter.trim().trim_matches('"');
+        let mut entry = UsageEntry::parse(rest, lenient)?;
+        entry.meter = match meters.iter().position(|known| known == meter) {
+            Some(index) => index as u8,
+            None => {
+                ensure!(
+                    meters.len() <= u8::MAX as usize,
+                    "More than {} meters in one file",
+                    u8::MAX as usize + 1
+                );
+                meters.push(meter.to_owned());
+                (meters.len() - 1) as u8
+            }
+        };
+        Ok(entry)
+    }
+
+    /// Splits a file that interleaves several meters into one dataset per meter, in the order
+    /// they first appear. A file with at most one meter comes back whole.
+    fn split_meters(self) -> Vec<Self> {
+        if self.meters.len() <= 1 {
+            return vec![self];
+        }
+        let mut split = self
+            .meters
+            .into_iter()
+            .map(|meter| Self {
+                address: self.address.clone(),
+                meters: vec![meter],
+                entries: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+        for mut entry in self.entries {
+            let meter = std::mem::take(&mut entry.meter);
+            split[meter as usize].entries.push(entry);
         }
+        split
     }
-}
 
-impl TemplateApp {
-    /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Default::default()
+    /// The meter this dataset is from, when the file named one.
+    fn meter(&self) -> Option<&str> {
+        self.meters.first().map(String::as_str)
     }
-}
 
-impl eframe::App for TemplateApp {
-    /// Called each time the UI needs repainting, which may be many times per second.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
-        // For inspiration and more examples, go to https://emilk.github.io/egui
+    /// Opens and parses the file at `path`. The portal format is streamed straight from disk;
+    /// the others are read whole and handed to [`Self::parse_file`].
+    fn parse_path(
+        path: &std::path::Path,
+        options: ParseOptions,
+    ) -> anyhow::Result<(Self, Vec<SkippedLine>)> {
+        use std::io::{BufRead, Read};
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            // The top panel is often a good place for a menu bar:
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+        let mut reader = std::io::BufReader::new(file);
+        let is_xml_extension = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("xml"));
+        // The buffer's first fill easily holds the three lines that identify the portal format.
+        let head = String::from_utf8_lossy(reader.fill_buf().context("Could not read the file")?);
+        if !is_xml_extension && head.lines().nth(2).and_then(portal_header).is_some() {
+            return Self::parse_reader(reader, options);
+        }
 
-            egui::menu::bar(ui, |ui| {
-                // NOTE: no File->Quit on web pages!
-                ui.menu_button("File", |ui| {
-                    if ui.button("Open").clicked() {
-                        if let Some(file) = rfd::FileDialog::new().pick_file() {
-                            if let Ok(file) = std::fs::read_to_string(file) {
-                                match UsageData::parse(&file) {
-                                    Ok(data) => {
-                                        if let Some(last) = data.entries.last() {
-                                            self.year = last.date.0;
-                                            self.month = last.date.1;
-                                            self.day = last.date.2;
-                                        }
-                                        self.data = Some(data);
-                                    }
-                                    Err(error) => self.error = Some(error.to_string()),
-                                }
-                            }
-                        }
-                    }
-                    let is_web = cfg!(target_arch = "wasm32");
-                    if !is_web {
-                        if ui.button("Quit").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
-                    }
-                });
-                ui.menu_button("About", |ui| {
-                    ui.hyperlink_to("Source code.", "https://github.com/The3gs/plot-electricity");
+        let mut input = Vec::new();
+        reader
+            .read_to_end(&mut input)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        Self::parse_file(path, &decode_text(&input)?, options)
+    }
 
-                    powered_by_egui_and_eframe(ui);
-                    egui::warn_if_debug_build(ui);
-                });
-                ui.add_space(16.0);
+    /// Picks a parser from the file's extension, falling back to sniffing its contents. Only the
+    /// portal format is tuned by `options`; the others ignore them.
+    fn parse_file(
+        path: &std::path::Path,
+        input: &str,
+        options: ParseOptions,
+    ) -> anyhow::Result<(Self, Vec<SkippedLine>)> {
+        let is_xml = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("xml"))
+            || input.trim_start().starts_with('<');
+        if is_xml {
+            Ok((green_button::parse_green_button(input)?, Vec::new()))
+        } else if input.lines().nth(2).and_then(portal_header).is_none()
+            && timestamp_csv::is_timestamp_csv(input)
+        {
+            timestamp_csv::parse_timestamp_csv(input)
+        } else {
+            Self::parse(input, options)
+        }
+    }
 
-                egui::widgets::global_theme_preference_buttons(ui);
+    /// Sorts the entries by when they start and drops any that repeat the one before them field
+    /// for field, as a file appended to twice would. The sort is stable, so entries that start
+    /// together but differ keep the order the file had them in.
+    fn normalize(&mut self) -> Normalized {
+        let reordered = self
+            .entries
+            .windows(2)
+            .any(|pair| pair[1].start() < pair[0].start());
+        if reordered {
+            self.entries.sort_by_key(UsageEntry::start);
+        }
+        let before = self.entries.len();
+        self.entries.dedup();
+        Normalized {
+            reordered,
+            duplicates_removed: before - self.entries.len(),
+        }
+    }
+
+    /// The first day kept when trimming to the last `days` days, counted back from the latest
+    /// entry rather than today so a file that's a few weeks old isn't emptied.
+    fn retention_cutoff(&self, days: u32) -> Option<NaiveDate> {
+        let last = self.entries.iter().map(UsageEntry::day).max()?;
+        last.checked_sub_days(Days::new(u64::from(days.max(1)) - 1))
+    }
+
+    /// Drops every entry before `cutoff`, returning how many were dropped.
+    fn trim_before(&mut self, cutoff: NaiveDate) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.day() >= cutoff);
+        before - self.entries.len()
+    }
+
+    /// The entries that count towards totals: all but those marked as excluded.
+    fn included(&self) -> impl Iterator<Item = &UsageEntry> {
+        self.entries.iter().filter(|entry| !entry.excluded)
+    }
+
+    /// Marks the entry starting at `start` on `day` as excluded, or includes it again.
+    fn toggle_excluded(&mut self, (day, hour, minute): (NaiveDate, u8, u8)) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| {
+            entry.day() == day
+                && (entry.interval_start_hour, entry.interval_start_minute) == (hour, minute)
+        }) {
+            entry.excluded = !entry.excluded;
+        }
+    }
+
+    /// Energy used over every entry. Energy adds up as-is however long each interval is, so a
+    /// file mixing hourly and 15-minute rows needs no weighting here.
+    fn total_energy(&self) -> f64 {
+        self.included().map(|entry| entry.kilowatt_hours).sum()
+    }
+
+    /// Average power draw in kW over the time the entries cover. Unlike energy, each entry's
+    /// power only counts for as long as its interval lasts, so this is total energy over total
+    /// hours rather than a plain mean of each entry's kW.
+    fn average_power(&self) -> Option<f64> {
+        let hours: f64 = self.included().map(UsageEntry::duration_hours).sum();
+        (hours > 0.0).then(|| self.total_energy() / hours)
+    }
+
+    /// With `snap`, counts each entry towards the hour nearest its start in hourly views, for
+    /// exports whose start times drift off the hour (like `08:59`). The exact times are kept
+    /// either way. Without `snap`, entries go back to the hour they start in.
+    fn snap_hour_buckets(&mut self, snap: bool) {
+        for entry in &mut self.entries {
+            entry.hour_bucket = snap.then(|| {
+                let minutes =
+                    entry.interval_start_hour as u32 * 60 + entry.interval_start_minute as u32;
+                // Rounding must not carry the day's last hour into the next day.
+                ((minutes + 30) / 60).min(23) as u8
             });
-        });
+        }
+    }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            // The central panel the region left after adding TopPanel's and SidePanel's
-            ui.heading("Power usage display");
+    /// The reporting interval, taken as the most common spacing between consecutive entries so
+    /// the odd gap or daylight-saving jump doesn't throw it off.
+    fn detected_interval(&self) -> Option<TimeDelta> {
+        let mut counts = BTreeMap::new();
+        for pair in self.entries.windows(2) {
+            let spacing = pair[1].start() - pair[0].start();
+            if spacing > TimeDelta::zero() {
+                *counts.entry(spacing).or_insert(0usize) += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(_, count)| count)
+            .map(|(spacing, _)| spacing)
+    }
 
-            if let Some(e) = &self.error {
-                ui.label(e);
-                if ui.button("Clear Error").clicked() {
-                    self.error = None;
-                }
+    /// The last day, if the file stops before the end of it, as when it was downloaded mid-day.
+    fn partial_day(&self) -> Option<NaiveDate> {
+        let last = self.entries.iter().map(UsageEntry::day).max()?;
+        let covered_until = self
+            .entries
+            .iter()
+            .filter(|entry| entry.day() == last)
+            .map(|entry| entry.start_hours() + entry.duration_hours())
+            .fold(0.0, f64::max);
+        // Allow for rounding in the minutes.
+        (covered_until < 24.0 - 1e-6).then_some(last)
+    }
+
+    /// Total usage for each day that passes `filter`.
+    fn daily_totals(&self, filter: &DayFilter) -> BTreeMap<NaiveDate, f64> {
+        let mut totals = BTreeMap::new();
+        for entry in self.included() {
+            let day = entry.day();
+            if filter.includes(day) {
+                *totals.entry(day).or_insert(0.0) += entry.kilowatt_hours;
             }
+        }
+        totals
+    }
 
-            ui.separator();
+    /// Each day of `range`'s net usage: what it imported less what it exported, which is what
+    /// the bill is based on. Positive readings are energy used and negative ones energy exported.
+    fn daily_net(&self, range: DateRange) -> Vec<(NaiveDate, f64)> {
+        let mut days = BTreeMap::new();
+        let in_range = |entry: &&UsageEntry| (range.start..=range.end).contains(&entry.day());
+        for entry in self.included().filter(in_range) {
+            let (imported, exported) = days.entry(entry.day()).or_insert((0.0, 0.0));
+            if entry.kilowatt_hours >= 0.0 {
+                *imported += entry.kilowatt_hours;
+            } else {
+                *exported -= entry.kilowatt_hours;
+            }
+        }
+        days.into_iter()
+            .map(|(day, (imported, exported))| (day, imported - exported))
+            .collect()
+    }
 
-            if let Some(data) = &self.data {
-                ui.label(&data.address);
-                let mut date = chrono::NaiveDate::from_ymd_opt(
-                    self.year as i32,
-                    self.month as u32,
-                    self.day as u32,
-                )
-                .unwrap();
-                ui.add(egui_extras::DatePickerButton::new(&mut date));
-                {
-                    use chrono::Datelike;
-                    self.year = date.year() as u16;
-                    self.month = date.month() as u8;
-                    self.day = date.day() as u8;
-                }
+    /// How many entries each day with data has, in date order. A full day has one per
+    /// interval, so a short count points at a gap or a partial export.
+    fn entries_per_day(&self) -> Vec<(NaiveDate, usize)> {
+        let mut counts = BTreeMap::new();
+        for entry in &self.entries {
+            *counts.entry(entry.day()).or_insert(0) += 1;
+        }
+        counts.into_iter().collect()
+    }
 
-                let plot = egui_plot::Plot::new("Power Usage Chart")
-                    .legend(egui_plot::Legend::default())
-                    .show_axes(true)
-                    .show_grid(false);
-                plot.show(ui, |plot_ui| {
-                    plot_ui.bar_chart(egui_plot::BarChart::new(
-                        format!("Usage for {}", date.format("%y-%m-%d").to_string()),
-                        data.entries
-                            .iter()
-                            .filter(|data_entry| {
-                                data_entry.date == (self.year, self.month, self.day)
-                            })
-                            .map(|data_entry| {
-                                egui_plot::Bar::new(
-                                    data_entry.interval_start_hour as f64,
-                                    data_entry.kilowatt_hours,
-                                )
-                                .width(1.0)
-                            })
-                            .collect::<Vec<_>>(),
-                    ))
-                });
+    /// `statistic` of the readings in each `bucket_hours`-long bucket of `range`, over the days
+    /// that pass `filter`. Buckets are keyed by how many buckets they are from the start of the
+    /// range, and ones with no readings are left out.
+    fn aggregate(
+        &self,
+        filter: &DayFilter,
+        range: DateRange,
+        bucket_hours: u32,
+        statistic: trend::Statistic,
+    ) -> BTreeMap<i64, f64> {
+        let mut buckets: BTreeMap<i64, Vec<f64>> = BTreeMap::new();
+        for entry in self.included() {
+            let day = entry.day();
+            if !(range.start..=range.end).contains(&day) || !filter.includes(day) {
+                continue;
             }
-        });
+            let hours = (day - range.start).num_days() * 24 + entry.hour_bucket() as i64;
+            buckets
+                .entry(hours / bucket_hours.max(1) as i64)
+                .or_default()
+                .push(entry.kilowatt_hours);
+        }
+        buckets
+            .into_iter()
+            .filter_map(|(bucket, readings)| Some((bucket, statistic.of(readings)?)))
+            .collect()
     }
-}
 
-fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
-    ui.horizontal(|ui| {
-        ui.spacing_mut().item_spacing.x = 0.0;
-        ui.label("Powered by ");
-        ui.hyperlink_to("egui", "https://github.com/emilk/egui");
-        ui.label(" and ");
-        ui.hyperlink_to(
-            "eframe",
-            "https://github.com/emilk/egui/tree/master/crates/eframe",
-        );
-        ui.label(".");
-    });
+    /// Usage in each hour of each day that passes `filter`, for the days table's sparklines.
+    fn hourly_by_day(&self, filter: &DayFilter) -> BTreeMap<NaiveDate, [f64; 24]> {
+        let mut days = BTreeMap::new();
+        for entry in self.included() {
+            let day = entry.day();
+            if filter.includes(day) {
+                days.entry(day).or_insert([0.0; 24])[entry.hour_bucket() as usize] +=
+                    entry.kilowatt_hours;
+            }
+        }
+        days
+    }
+
+    /// [`Self::daily_totals`], grouped by `(year, month)`.
+    fn monthly_daily_totals(
+        &self,
+        filter: &DayFilter,
+    ) -> BTreeMap<(i32, u32), BTreeMap<NaiveDate, f64>> {
+        let mut months: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+        for (day, total) in self.daily_totals(filter) {
+            months
+                .entry((day.year(), day.month()))
+                .or_default()
+                .insert(day, total);
+        }
+        months
+    }
+
+    /// Average usage for each hour of the day, across the days that pass `filter`.
+    fn typical_day(&self, filter: &DayFilter) -> [f64; 24] {
+        let mut sums = [0.0; 24];
+        let mut days = BTreeSet::new();
+        for entry in self.included() {
+            let day = entry.day();
+            if filter.includes(day) {
+                sums[entry.hour_bucket() as usize] += entry.kilowatt_hours;
+                days.insert(day);
+            }
+        }
+        if !days.is_empty() {
+            for sum in &mut sums {
+                *sum /= days.len() as f64;
+            }
+        }
+        sums
+    }
+
+    /// Average usage for each hour of each weekday (Monday first), across the days that pass
+    /// `filter`.
+    fn weekday_hour_averages(&self, filter: &DayFilter) -> [[f64; 24]; 7] {
+        let mut sums = [[0.0; 24]; 7];
+        let mut days = BTreeSet::new();
+        for entry in self.included() {
+            let day = entry.day();
+            if filter.includes(day) {
+                let weekday = day.weekday().num_days_from_monday() as usize;
+                sums[weekday][entry.hour_bucket() as usize] += entry.kilowatt_hours;
+                days.insert(day);
+            }
+        }
+        for (weekday, hours) in sums.iter_mut().enumerate() {
+            let count = days
+                .iter()
+                .filter(|day| day.weekday().num_days_from_monday() as usize == weekday)
+                .count();
+            if count > 0 {
+                for sum in hours {
+                    *sum /= count as f64;
+                }
+            }
+        }
+        sums
+    }
+}
+
+impl Default for TemplateApp {
+    fn default() -> Self {
+        Self {
+            version: migration::CURRENT_VERSION,
+            error: None,
+            show_error_causes: true,
+            data: None,
+            other_meters: Vec::new(),
+            comparisons: Vec::new(),
+            year: 2025,
+            month: 6,
+            day: 21,
+            day_filter: DayFilter::default(),
+            range: DateRange::default(),
+            display: DisplaySettings::default(),
+            view: View::default(),
+            compared_weekday: Weekday::Mon,
+            on_open: OnOpen::default(),
+            rates: RateSchedule::default(),
+            alternative_rates: RateSchedule::default(),
+            projection_days: 14,
+            breakdown_cycle: None,
+            stale_after_days: 7,
+            retention_days: 365,
+            confirm_trim: false,
+            exclude_partial_day: false,
+            lenient_parsing: false,
+            skip_totals_rows: true,
+            normalize_on_load: true,
+            snap_hour_buckets: false,
+            csv_format: csv_export::CsvFormat::default(),
+            budget: None,
+            emissions_factor: emissions::DEFAULT_FACTOR,
+            grid_intensity: None,
+            daily_emissions: None,
+            signature_settings: signatures::SignatureSettings::default(),
+            signatures: None,
+            trend_settings: trend::TrendSettings::default(),
+            bookmarks: Vec::new(),
+            // eframe's own default.
+            autosave_seconds: 30,
+            data_path: None,
+            summary: None,
+            entry_costs: None,
+            trend: None,
+            show_shortcuts: false,
+            failed_input: None,
+            show_raw_input: false,
+            load_stats: None,
+            note_query: String::new(),
+            usage_bounds: search::UsageBounds::default(),
+            new_holiday: NaiveDate::default(),
+            date_input: String::new(),
+            date_input_invalid: false,
+            new_bookmark: String::new(),
+            error_causes: Vec::new(),
+        }
+    }
+}
+
+impl TemplateApp {
+    /// Called once before the first frame.
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        // Load previous app state (if any).
+        let Some(storage) = cc.storage else {
+            return Default::default();
+        };
+        match eframe::get_value::<Self>(storage, eframe::APP_KEY) {
+            Some(mut app) => {
+                if let Some(warning) = migration::migrate(&mut app) {
+                    app.error = Some(warning);
+                }
+                app
+            }
+            None if storage.get_string(eframe::APP_KEY).is_some() => Self {
+                error: Some("Saved state couldn't be read, so the app was reset".to_owned()),
+                ..Default::default()
+            },
+            None => Default::default(),
+        }
+    }
+
+    fn selected_date(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year as i32, self.month as u32, self.day as u32).unwrap()
+    }
+
+    fn select_date(&mut self, date: NaiveDate) {
+        self.year = date.year() as u16;
+        self.month = date.month() as u8;
+        self.day = date.day() as u8;
+    }
+
+    /// Asks for a file and parses it as `target`, reporting any problem in `self.error`.
+    fn pick_and_parse(&mut self, target: Target) -> Option<(std::path::PathBuf, UsageData)> {
+        let path = rfd::FileDialog::new().pick_file()?;
+        let data = self.load_from_path(&path, true, target)?;
+        Some((path, data))
+    }
+
+    /// Loads the file at `path` as `target`, from its cache if `use_cache` and the cache is
+    /// current, and reports any problem in `self.error`. Only loads of the main file are
+    /// described in `self.load_stats`.
+    fn load_from_path(
+        &mut self,
+        path: &std::path::Path,
+        use_cache: bool,
+        target: Target,
+    ) -> Option<UsageData> {
+        let started = web_time::Instant::now();
+        if use_cache {
+            if let Some(mut data) = cache::load(path, self.parse_options()) {
+                let normalized = self.finish_loading(&mut data);
+                if target == Target::Main {
+                    self.load_stats = Some(LoadStats {
+                        entries: data.entries.len(),
+                        duration: started.elapsed(),
+                        skipped: Vec::new(),
+                        from_cache: true,
+                        normalized,
+                    });
+                }
+                return Some(data);
+            }
+        }
+
+        let options = self.parse_options();
+        match UsageData::parse_path(path, options) {
+            Ok((mut data, skipped)) => {
+                // Rows a parse skipped would be missing from the cache with no warning
+                // the next time, so only complete parses are cached. The cache holds the data
+                // as parsed, so what's done to it after follows the settings at each load.
+                // Caching is only an optimisation, so a cache that can't be written is no
+                // reason to fail the load.
+                if skipped.is_empty() {
+                    let _ = cache::store(path, options, &data);
+                }
+                let normalized = self.finish_loading(&mut data);
+                if target == Target::Main {
+                    self.load_stats = Some(LoadStats {
+                        entries: data.entries.len(),
+                        duration: started.elapsed(),
+                        skipped,
+                        from_cache: false,
+                        normalized,
+                    });
+                }
+                Some(data)
+            }
+            Err(error) => {
+                // Only read the text back in for showing around the bad line once it's needed.
+                self.failed_input = std::fs::read(path)
+                    .ok()
+                    .and_then(|bytes| Some(decode_text(&bytes).ok()?.into_owned()))
+                    .map(|text| FailedInput {
+                        path: Some(path.to_owned()),
+                        target,
+                        line: error.downcast_ref::<OnLine>().map(|line| line.0),
+                        text,
+                    });
+                self.error_causes = error_causes(&error);
+                self.error = Some(error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Normalizes freshly parsed or cached `data` if that's asked for on load, and buckets it
+    /// into hours as set, reporting what normalizing changed.
+    fn finish_loading(&self, data: &mut UsageData) -> Normalized {
+        let normalized = if self.normalize_on_load {
+            data.normalize()
+        } else {
+            Normalized::default()
+        };
+        data.snap_hour_buckets(self.snap_hour_buckets);
+        normalized
+    }
+
+    /// Sets [`Self::snap_hour_buckets`], re-bucketing the data already loaded to match and
+    /// dropping the trend computed from the old buckets.
+    fn set_snap_hour_buckets(&mut self, snap: bool) {
+        self.snap_hour_buckets = snap;
+        for data in self
+            .data
+            .iter_mut()
+            .chain(self.comparisons.iter_mut().map(|dataset| &mut dataset.data))
+        {
+            data.snap_hour_buckets(snap);
+        }
+        self.trend = None;
+    }
+
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            lenient: self.lenient_parsing,
+            skip_totals_rows: self.skip_totals_rows,
+        }
+    }
+
+    /// Parses the text of the file that last failed again, adjusted by `retry`, and opens it as
+    /// what it was being loaded as if that works.
+    fn retry_parse(&mut self, retry: raw_input::Retry) {
+        let options = self.parse_options();
+        let Some(input) = &mut self.failed_input else {
+            return;
+        };
+        // Without a path there's nothing to open the data as, so the error stays up.
+        let Some(path) = input.path.clone() else {
+            return;
+        };
+        let target = input.target;
+        let started = web_time::Instant::now();
+        match UsageData::parse(&retry.apply(&input.text), options) {
+            Ok((mut data, skipped)) => {
+                let normalized = self.finish_loading(&mut data);
+                self.error = None;
+                self.error_causes.clear();
+                match target {
+                    Target::Main => {
+                        self.load_stats = Some(LoadStats {
+                            entries: data.entries.len(),
+                            duration: started.elapsed(),
+                            skipped,
+                            from_cache: false,
+                            normalized,
+                        });
+                        self.show_file(path, data);
+                    }
+                    Target::Comparison => {
+                        self.failed_input = None;
+                        self.push_comparison(&path, data);
+                    }
+                }
+            }
+            Err(error) => {
+                input.line = error.downcast_ref::<OnLine>().map(|line| line.0);
+                self.error_causes = error_causes(&error);
+                self.error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Parses the open file again, ignoring and replacing its cache.
+    fn rebuild_cache(&mut self) {
+        let Some(path) = self.data_path.clone() else {
+            return;
+        };
+        if let Some(data) = self.load_from_path(&path, false, Target::Main) {
+            let mut meters = data.split_meters().into_iter();
+            self.data = meters.next();
+            self.other_meters = meters.collect();
+            self.summary = None;
+            self.entry_costs = None;
+            self.trend = None;
+            self.signatures = None;
+            self.daily_emissions = None;
+            self.failed_input = None;
+        }
+    }
+
+    /// Asks for a file and loads it, jumping to its last day unless [`OnOpen`] says otherwise.
+    fn open_file(&mut self) {
+        if let Some((path, data)) = self.pick_and_parse(Target::Main) {
+            self.show_file(path, data);
+        }
+    }
+
+    /// Makes `data`, loaded from `path`, the file being looked at.
+    fn show_file(&mut self, path: std::path::PathBuf, data: UsageData) {
+        self.data_path = Some(path);
+        let mut meters = data.split_meters().into_iter();
+        let Some(data) = meters.next() else {
+            return;
+        };
+        self.other_meters = meters.collect();
+        if let (Some(first), Some(last)) = (
+            data.entries.iter().map(UsageEntry::day).min(),
+            data.entries.iter().map(UsageEntry::day).max(),
+        ) {
+            let keep_date = self.on_open == OnOpen::RememberLastViewed
+                && (first..=last).contains(&self.selected_date());
+            if !keep_date {
+                if let Some(entry) = data.entries.last() {
+                    self.year = entry.date.0;
+                    self.month = entry.date.1;
+                    self.day = entry.date.2;
+                }
+            }
+            self.range = DateRange {
+                start: first,
+                end: last,
+            };
+        }
+        self.data = Some(data);
+        self.summary = None;
+        self.entry_costs = None;
+        self.trend = None;
+        self.signatures = None;
+        self.daily_emissions = None;
+        self.failed_input = None;
+    }
+
+    /// Picks which of the file's meters `data` shows, swapping it with the one picked.
+    fn meter_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(data) = &mut self.data else {
+            return;
+        };
+        let mut picked = None;
+        egui::ComboBox::from_id_salt("meter")
+            .selected_text(data.meter().unwrap_or_default())
+            .show_ui(ui, |ui| {
+                for (i, other) in self.other_meters.iter().enumerate() {
+                    if ui
+                        .selectable_label(false, other.meter().unwrap_or_default())
+                        .clicked()
+                    {
+                        picked = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = picked {
+            std::mem::swap(data, &mut self.other_meters[i]);
+            self.other_meters.sort_by(|a, b| a.meter().cmp(&b.meter()));
+            self.summary = None;
+            self.entry_costs = None;
+            self.trend = None;
+            self.signatures = None;
+            self.daily_emissions = None;
+        }
+    }
+
+    /// The retention setting and "Trim now", which says how much it would discard and waits for
+    /// a confirmation before discarding it. Every meter in the file is trimmed to the same day.
+    fn retention_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(data) = &mut self.data else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.label("Keep the last");
+            ui.add(
+                egui::DragValue::new(&mut self.retention_days)
+                    .range(1..=3650)
+                    .suffix(" days"),
+            );
+            if ui.button("Trim now").clicked() {
+                self.confirm_trim = true;
+            }
+        });
+        let Some(cutoff) = data.retention_cutoff(self.retention_days) else {
+            return;
+        };
+        if !self.confirm_trim {
+            return;
+        }
+        let discarded = std::iter::once(&*data)
+            .chain(&self.other_meters)
+            .flat_map(|data| &data.entries)
+            .filter(|entry| entry.day() < cutoff)
+            .count();
+        if discarded == 0 {
+            ui.weak("Nothing is older than that.");
+            if ui.small_button("OK").clicked() {
+                self.confirm_trim = false;
+            }
+            return;
+        }
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!(
+                "⚠ This discards {discarded} entries from before {}. They are gone from the saved \
+                 state too, and only reopening the file brings them back.",
+                self.display.date_format.format(cutoff)
+            ),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Discard").clicked() {
+                data.trim_before(cutoff);
+                for other in &mut self.other_meters {
+                    other.trim_before(cutoff);
+                }
+                self.range.start = self.range.start.max(cutoff);
+                self.range.end = self.range.end.max(self.range.start);
+                self.summary = None;
+                self.entry_costs = None;
+                self.trend = None;
+                self.signatures = None;
+                self.daily_emissions = None;
+                self.confirm_trim = false;
+            }
+            if ui.button("Cancel").clicked() {
+                self.confirm_trim = false;
+            }
+        });
+    }
+
+    /// Just the day's chart, with none of the controls around it.
+    fn report_ui(&mut self, ctx: &egui::Context) {
+        let date = self.selected_date();
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let Some(data) = &self.data else {
+                ui.label("Open a file to show its chart. Press Esc to leave report mode.");
+                return;
+            };
+            if let Err(error) = day_view::day_view_ui(
+                ui,
+                data,
+                &self.comparisons,
+                &self.day_filter,
+                self.entry_costs.as_ref().map_or(&[], |costs| &costs.costs),
+                &mut self.display,
+                date,
+            ) {
+                self.error_causes = error_causes(&error);
+                self.error = Some(error.to_string());
+            }
+        });
+    }
+
+    /// Asks for a file and adds it to the comparisons.
+    fn add_comparison(&mut self) {
+        if let Some((path, data)) = self.pick_and_parse(Target::Comparison) {
+            self.push_comparison(&path, data);
+        }
+    }
+
+    /// Adds `data`, loaded from `path`, to the comparisons, labelled with the file's name.
+    fn push_comparison(&mut self, path: &std::path::Path, data: UsageData) {
+        let label = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dataset = Dataset::new(label, data, self.comparisons.len());
+        self.comparisons.push(dataset);
+    }
+}
+
+impl eframe::App for TemplateApp {
+    /// Called by the framework to save state before shutdown, and every
+    /// [`Self::auto_save_interval`] while running.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    /// How often eframe calls [`Self::save`] while running, so a crash loses at most this much.
+    fn auto_save_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.autosave_seconds)
+    }
+
+    /// Called each time the UI needs repainting, which may be many times per second.
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
+        // For inspiration and more examples, go to https://emilk.github.io/egui
+
+        for action in shortcuts::pressed(ctx) {
+            match action {
+                Action::Open => self.open_file(),
+                Action::PreviousDay => self.select_date(self.selected_date() - Days::new(1)),
+                Action::NextDay => self.select_date(self.selected_date() + Days::new(1)),
+                Action::ToggleHelp => self.show_shortcuts = !self.show_shortcuts,
+                Action::ToggleReportMode => self.display.report_mode = !self.display.report_mode,
+                Action::CycleTheme => ctx.set_theme(shortcuts::next_theme(ctx)),
+            }
+        }
+        if let Some(toggle) = day_view::take_exclusion_toggle(ctx) {
+            if let Some(data) = &mut self.data {
+                data.toggle_excluded(toggle);
+                self.summary = None;
+                self.entry_costs = None;
+                self.trend = None;
+                self.signatures = None;
+                self.daily_emissions = None;
+            }
+        }
+        if let Some(visible) = day_view::take_visible_export(ctx) {
+            let csv = csv_export::bars_csv(visible.date, &visible.bars, self.csv_format);
+            if let Err(error) = csv_export::save_csv(&csv, &format!("{}-visible.csv", visible.date))
+            {
+                self.error_causes = error_causes(&error);
+                self.error = Some(error.to_string());
+            }
+        }
+        if let Some(data) = &self.data {
+            if self
+                .entry_costs
+                .as_ref()
+                .map_or(true, |costs| costs.schedule != self.rates)
+            {
+                self.entry_costs = Some(rates::EntryCosts::new(data, &self.rates));
+            }
+            if self
+                .daily_emissions
+                .as_ref()
+                .map_or(true, |emissions| !emissions.is_for(self.emissions_factor))
+            {
+                self.daily_emissions = Some(emissions::DailyEmissions::new(
+                    data,
+                    self.grid_intensity.as_ref(),
+                    self.emissions_factor,
+                ));
+            }
+        }
+
+        if self.display.report_mode {
+            if ctx.input(|input| input.key_pressed(egui::Key::Escape)) {
+                self.display.report_mode = false;
+            } else {
+                self.report_ui(ctx);
+                return;
+            }
+        }
+        shortcuts::help_window(ctx, &mut self.show_shortcuts);
+        if let Some(failed_input) = &self.failed_input {
+            raw_input::raw_input_window(ctx, failed_input, &mut self.show_raw_input);
+        }
+
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            // The top panel is often a good place for a menu bar:
+
+            egui::menu::bar(ui, |ui| {
+                // NOTE: no File->Quit on web pages!
+                ui.menu_button("File", |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("Open")
+                                .shortcut_text(shortcuts::shortcut_text(ctx, Action::Open)),
+                        )
+                        .clicked()
+                    {
+                        self.open_file();
+                    }
+                    if ui.button("Add comparison").clicked() {
+                        self.add_comparison();
+                        ui.close_menu();
+                    }
+                    if ui.button("Save sample file").clicked() {
+                        if let Err(error) = csv_export::save_csv(&sample_file(), "sample.csv") {
+                            self.error_causes = error_causes(&error);
+                            self.error = Some(error.to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.lenient_parsing, "Skip bad rows")
+                        .on_hover_text(
+                            "Also accepts rows that leave out the empty note column or end in an \
+                             extra comma",
+                        );
+                    ui.checkbox(&mut self.skip_totals_rows, "Skip totals rows");
+                    ui.checkbox(
+                        &mut self.normalize_on_load,
+                        "Sort rows and merge duplicates",
+                    );
+                    let mut snap = self.snap_hour_buckets;
+                    if ui
+                        .checkbox(&mut snap, "Snap starts to the nearest hour")
+                        .changed()
+                    {
+                        self.set_snap_hour_buckets(snap);
+                    }
+                    if ui
+                        .add_enabled(self.data_path.is_some(), egui::Button::new("Rebuild cache"))
+                        .clicked()
+                    {
+                        self.rebuild_cache();
+                        ui.close_menu();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Save state every");
+                        ui.add(
+                            egui::DragValue::new(&mut self.autosave_seconds)
+                                .range(5..=600)
+                                .suffix(" s"),
+                        );
+                    });
+                    ui.separator();
+                    if let Some(data) = &self.data {
+                        let mut csv = None;
+                        if ui.button("Export entries as CSV").clicked() {
+                            csv = Some((
+                                csv_export::entries_csv(&data.entries, self.csv_format),
+                                "entries.csv",
+                            ));
+                        }
+                        if ui.button("Export daily totals in range as CSV").clicked() {
+                            let daily_totals =
+                                self.range.days(&data.daily_totals(&self.day_filter));
+                            csv = Some((
+                                csv_export::daily_totals_csv(&daily_totals, self.csv_format),
+                                "daily-totals.csv",
+                            ));
+                        }
+                        ui.menu_button("CSV format", |ui| {
+                            csv_export::csv_format_ui(ui, &mut self.csv_format);
+                        });
+                        if let Some((csv, file_name)) = csv {
+                            if let Err(error) = csv_export::save_csv(&csv, file_name) {
+                                self.error_causes = error_causes(&error);
+                                self.error = Some(error.to_string());
+                            }
+                            ui.close_menu();
+                        }
+                        if ui.button("Export report for range as HTML").clicked() {
+                            let file_name =
+                                format!("report-{}-to-{}.html", self.range.start, self.range.end);
+                            let saved = report::range_report(
+                                data,
+                                self.range,
+                                &self.day_filter,
+                                &self.display,
+                            )
+                            .and_then(|html| csv_export::save_html(&html, &file_name));
+                            if let Err(error) = saved {
+                                self.error_causes = error_causes(&error);
+                                self.error = Some(error.to_string());
+                            }
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                    }
+                    if ui.button("Export Settings").clicked() {
+                        if let Err(error) = profile::export_settings(self) {
+                            self.error_causes = error_causes(&error);
+                            self.error = Some(error.to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button("Import Settings").clicked() {
+                        if let Err(error) = profile::import_settings(self) {
+                            self.error_causes = error_causes(&error);
+                            self.error = Some(error.to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    let is_web = cfg!(target_arch = "wasm32");
+                    if !is_web && ui.button("Quit").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+                ui.menu_button("View", |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("Report mode").shortcut_text(
+                                shortcuts::shortcut_text(ctx, Action::ToggleReportMode),
+                            ),
+                        )
+                        .clicked()
+                    {
+                        self.display.report_mode = true;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Bookmarks", |ui| {
+                    let selected = self.selected_date();
+                    if let Some(day) = bookmarks::bookmarks_menu_ui(
+                        ui,
+                        &mut self.bookmarks,
+                        selected,
+                        &mut self.new_bookmark,
+                        self.display.date_format,
+                    ) {
+                        self.select_date(day);
+                        self.view = View::Day;
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("About", |ui| {
+                    if ui
+                        .add(
+                            egui::Button::new("Keyboard shortcuts")
+                                .shortcut_text(shortcuts::shortcut_text(ctx, Action::ToggleHelp)),
+                        )
+                        .clicked()
+                    {
+                        self.show_shortcuts = true;
+                        ui.close_menu();
+                    }
+                    ui.hyperlink_to("Source code.", "https://github.com/The3gs/plot-electricity");
+
+                    powered_by_egui_and_eframe(ui);
+                    egui::warn_if_debug_build(ui);
+                });
+                ui.add_space(16.0);
+
+                egui::widgets::global_theme_preference_buttons(ui);
+            });
+        });
+
+        if let Some(data) = &self.data {
+            if self.summary.as_ref().map_or(true, |summary| {
+                summary.cycle_start_day != self.rates.cycle_start_day
+            }) {
+                self.summary = DatasetSummary::new(data, &self.rates);
+            }
+        }
+
+        egui::SidePanel::left("side_panel").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if !self.other_meters.is_empty() {
+                    ui.heading("Meter");
+                    self.meter_ui(ui);
+                    ui.separator();
+                }
+                ui.heading("Filters");
+                ui.checkbox(&mut self.day_filter.exclude_weekends, "Exclude weekends");
+                ui.checkbox(&mut self.day_filter.exclude_holidays, "Exclude holidays");
+                ui.checkbox(
+                    &mut self.exclude_partial_day,
+                    "Leave a partial last day out of averages",
+                );
+                ui.collapsing("Holidays", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui_extras::DatePickerButton::new(&mut self.new_holiday)
+                                .id_salt("new_holiday"),
+                        );
+                        if ui.button("Add").clicked() {
+                            self.day_filter.holidays.insert(self.new_holiday);
+                        }
+                    });
+                    let mut removed = None;
+                    for holiday in &self.day_filter.holidays {
+                        ui.horizontal(|ui| {
+                            ui.label(self.display.date_format.format(*holiday));
+                            if ui.small_button("Remove").clicked() {
+                                removed = Some(*holiday);
+                            }
+                        });
+                    }
+                    if let Some(holiday) = removed {
+                        self.day_filter.holidays.remove(&holiday);
+                    }
+                });
+
+                ui.separator();
+                ui.heading("Display");
+                display::display_settings_ui(ui, &mut self.display);
+
+                if let Some(data) = &self.data {
+                    ui.separator();
+                    ui.heading("Notes");
+                    tags::tag_filter_ui(ui, &mut self.display.tags, data);
+                    let note_match = search::note_search_ui(
+                        ui,
+                        data,
+                        self.display.date_format,
+                        self.display.time_format,
+                        self.display.note_length,
+                        &mut self.note_query,
+                    );
+
+                    ui.separator();
+                    ui.heading("Find usage");
+                    let usage_match = search::usage_search_ui(
+                        ui,
+                        data,
+                        self.display.date_format,
+                        self.display.time_format,
+                        self.display.number_format,
+                        &mut self.usage_bounds,
+                    );
+                    if let Some(day) = note_match.or(usage_match) {
+                        self.select_date(day);
+                        self.view = View::Day;
+                    }
+                }
+
+                ui.separator();
+                ui.heading("On open");
+                ui.radio_value(
+                    &mut self.on_open,
+                    OnOpen::JumpToLatest,
+                    "Jump to latest day",
+                );
+                ui.radio_value(
+                    &mut self.on_open,
+                    OnOpen::RememberLastViewed,
+                    "Remember last viewed date",
+                );
+                ui.separator();
+
+                ui.heading("Comparisons");
+                comparison::comparisons_ui(
+                    ui,
+                    &mut self.comparisons,
+                    self.data.as_ref(),
+                    self.display.date_format,
+                    self.display.number_format,
+                );
+
+                ui.separator();
+                ui.heading("Rates");
+                rates::rate_schedule_ui(ui, &mut self.rates, self.display.number_format.currency);
+                budget::budget_ui(ui, &mut self.budget);
+                match emissions::emissions_settings_ui(
+                    ui,
+                    &mut self.emissions_factor,
+                    &mut self.grid_intensity,
+                ) {
+                    Ok(true) => self.daily_emissions = None,
+                    Ok(false) => {}
+                    Err(error) => {
+                        self.error_causes = error_causes(&error);
+                        self.error = Some(error.to_string());
+                    }
+                }
+
+                if let Some(data) = &self.data {
+                    ui.separator();
+                    ui.collapsing("Recurring loads", |ui| {
+                        signatures::signatures_ui(
+                            ui,
+                            data,
+                            &mut self.signature_settings,
+                            &mut self.signatures,
+                            self.display.number_format,
+                        );
+                    });
+                    ui.collapsing("Data retention", |ui| self.retention_ui(ui));
+                }
+            });
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // The central panel the region left after adding TopPanel's and SidePanel's
+            ui.heading("Power usage display");
+
+            let mut retry = None;
+            if let Some(e) = &self.error {
+                ui.label(e);
+                if self.show_error_causes && !self.error_causes.is_empty() {
+                    egui::ScrollArea::vertical()
+                        .id_salt("error_causes")
+                        .max_height(100.0)
+                        .show(ui, |ui| {
+                            for cause in &self.error_causes {
+                                ui.weak(format!("Caused by: {cause}"));
+                            }
+                        });
+                }
+                ui.horizontal(|ui| {
+                    if !self.error_causes.is_empty() {
+                        ui.checkbox(&mut self.show_error_causes, "Show causes");
+                    }
+                    if let Some(line) = self.failed_input.as_ref().and_then(|input| input.line) {
+                        if ui
+                            .button(format!("Show raw file around line {line}"))
+                            .clicked()
+                        {
+                            self.show_raw_input = true;
+                        }
+                    }
+                    if let Some(input) = &self.failed_input {
+                        if input.path.is_some() {
+                            for choice in raw_input::Retry::ALL {
+                                if ui.button(choice.label()).clicked() {
+                                    retry = Some(choice);
+                                }
+                            }
+                        }
+                    }
+                    if ui.button("Clear Error").clicked() {
+                        self.error = None;
+                        self.error_causes.clear();
+                        self.failed_input = None;
+                    }
+                });
+            }
+
+            if let Some(retry) = retry {
+                self.retry_parse(retry);
+            }
+
+            ui.separator();
+
+            if let Some(data) = &self.data {
+                ui.label(&data.address);
+                if let Some(stats) = &self.load_stats {
+                    ui.weak(format!(
+                        "Loaded {} entries in {} ms{}",
+                        display::group_thousands(stats.entries),
+                        stats.duration.as_millis(),
+                        if stats.from_cache { " from cache" } else { "" }
+                    ));
+                    if stats.normalized.reordered {
+                        ui.weak("Sorted the rows into time order");
+                    }
+                    if stats.normalized.duplicates_removed > 0 {
+                        ui.weak(format!(
+                            "Merged {} duplicate rows",
+                            display::group_thousands(stats.normalized.duplicates_removed)
+                        ));
+                    }
+                    if !stats.skipped.is_empty() {
+                        ui.colored_label(
+                            ui.visuals().warn_fg_color,
+                            format!(
+                                "Loaded {} rows, skipped {} (see warnings)",
+                                display::group_thousands(stats.entries),
+                                display::group_thousands(stats.skipped.len())
+                            ),
+                        );
+                        ui.collapsing("Warnings", |ui| {
+                            for skipped in &stats.skipped {
+                                ui.label(format!("Line {}: {}", skipped.line, skipped.message));
+                            }
+                        });
+                    }
+                }
+                let number_format = self.display.number_format;
+                if let Some(summary) = &self.summary {
+                    ui.label(
+                        egui::RichText::new(number_format.kilowatt_hours(summary.total)).size(28.0),
+                    );
+                    let cost = summary.cost(&self.rates);
+                    display::cost_label(
+                        ui,
+                        cost,
+                        format!(
+                            "From {} to {}, estimated cost {}",
+                            self.display.date_format.format(summary.first_day),
+                            self.display.date_format.format(summary.last_day),
+                            number_format.cost(cost)
+                        ),
+                    );
+                    if let Some(emissions) = &self.daily_emissions {
+                        ui.label(format!(
+                            "Estimated emissions: {}",
+                            emissions::kilograms(
+                                number_format,
+                                emissions.total(summary.first_day, |_| true)
+                            )
+                        ));
+                    }
+                    if let Some(interval) = summary.interval {
+                        ui.label(format!("Interval: {} min", interval.num_minutes()));
+                    }
+                    if let Some(power) = summary.average_power {
+                        ui.label(format!("Average draw: {} kW", number_format.energy(power)));
+                    }
+                    ui.horizontal(|ui| {
+                        freshness_ui(
+                            ui,
+                            summary.latest_reading,
+                            self.display.date_format,
+                            self.display.time_format,
+                            self.stale_after_days,
+                        );
+                        ui.add(
+                            egui::DragValue::new(&mut self.stale_after_days)
+                                .range(1..=365)
+                                .prefix("stale after ")
+                                .suffix(" days"),
+                        );
+                    });
+                    if let Some(day) = summary.partial_day {
+                        ui.weak(format!(
+                            "{} is a partial day: the file ends before midnight",
+                            self.display.date_format.format(day)
+                        ));
+                    }
+                    ui.horizontal(|ui| {
+                        match rates::project_cost(
+                            &summary.complete_days(&summary.daily_totals, self.exclude_partial_day),
+                            self.projection_days,
+                            30,
+                            &self.rates,
+                        ) {
+                            Some(projection) => display::cost_label(
+                                ui,
+                                projection.cost,
+                                format!(
+                                    "Projected 30-day cost: {} based on last {} days, ignoring the day filter (estimate)",
+                                    number_format.cost(projection.cost),
+                                    projection.days
+                                ),
+                            ),
+                            None => ui.label("Not enough data to project a 30-day cost"),
+                        };
+                        ui.add(
+                            egui::DragValue::new(&mut self.projection_days)
+                                .range(1..=365)
+                                .suffix(" days"),
+                        );
+                    });
+                    if let Some(progress) = self.budget.and_then(|budget| {
+                        budget::CycleProgress::new(&summary.daily_totals, &self.rates, budget)
+                    }) {
+                        budget::progress_ui(ui, &progress, number_format);
+                    }
+                    ui.collapsing("Bill breakdown", |ui| {
+                        let export = rates::breakdown_ui(
+                            ui,
+                            &self.rates,
+                            &summary.cycle_totals,
+                            &mut self.breakdown_cycle,
+                            self.display.date_format,
+                            number_format,
+                        );
+                        if let Some((start, rows)) = export {
+                            if let Err(error) = csv_export::save_csv(
+                                &csv_export::breakdown_csv(&rows, self.csv_format, number_format),
+                                &format!("bill-{start}.csv"),
+                            ) {
+                                self.error_causes = error_causes(&error);
+                                self.error = Some(error.to_string());
+                            }
+                        }
+                    });
+                    ui.collapsing("Compare plans", |ui| {
+                        ui.push_id("alternative_rates", |ui| {
+                            rates::rate_schedule_ui(
+                                ui,
+                                &mut self.alternative_rates,
+                                number_format.currency,
+                            );
+                        });
+                        ui.separator();
+                        rates::plan_comparison_ui(
+                            ui,
+                            &self.rates,
+                            &self.alternative_rates,
+                            &summary.cycle_totals,
+                            self.breakdown_cycle,
+                            self.display.date_format,
+                            number_format,
+                        );
+                    });
+                    let mut recent_cycles = summary.cycle_totals.values().rev();
+                    if let (Some(&current), Some(&previous)) =
+                        (recent_cycles.next(), recent_cycles.next())
+                    {
+                        if let (Some(emissions), Some(&cycle_start)) = (
+                            &self.daily_emissions,
+                            summary.cycle_totals.keys().next_back(),
+                        ) {
+                            ui.label(format!(
+                                "Emissions this cycle: {}",
+                                emissions::kilograms(
+                                    number_format,
+                                    emissions.total(cycle_start, |_| true)
+                                )
+                            ));
+                        }
+                        if previous > 0.0 {
+                            ui.label("This cycle against last cycle");
+                            gauge::cycle_gauge_ui(ui, current, previous, number_format);
+                        }
+                    }
+                }
+                let mut date = self.selected_date();
+                ui.horizontal(|ui| {
+                    ui.add(egui_extras::DatePickerButton::new(&mut date));
+
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.date_input)
+                            .hint_text("YYYY-MM-DD")
+                            .desired_width(90.0),
+                    );
+                    if response.changed() {
+                        self.date_input_invalid = false;
+                    }
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        match NaiveDate::parse_from_str(self.date_input.trim(), "%Y-%m-%d") {
+                            Ok(parsed) => date = parsed,
+                            Err(_) => self.date_input_invalid = true,
+                        }
+                    }
+                    if self.date_input_invalid {
+                        ui.painter().rect_stroke(
+                            response.rect,
+                            2.0,
+                            egui::Stroke::new(1.0, ui.visuals().error_fg_color),
+                            egui::StrokeKind::Outside,
+                        );
+                    }
+
+                    ui.menu_button("Export day", |ui| {
+                        let day_entries =
+                            || data.entries.iter().filter(|entry| entry.day() == date);
+                        let result = if ui.button("As CSV").clicked() {
+                            ui.close_menu();
+                            csv_export::save_csv(
+                                &csv_export::entries_csv(day_entries(), self.csv_format),
+                                &format!("{date}.csv"),
+                            )
+                        } else if ui.button("As JSON").clicked() {
+                            ui.close_menu();
+                            csv_export::entries_json(day_entries()).and_then(|json| {
+                                csv_export::save_json(&json, &format!("{date}.json"))
+                            })
+                        } else {
+                            Ok(())
+                        };
+                        if let Err(error) = result {
+                            self.error_causes = error_causes(&error);
+                            self.error = Some(error.to_string());
+                        }
+                    });
+                });
+
+                if let Some(summary) = &self.summary {
+                    range::range_ui(
+                        ui,
+                        &mut self.range,
+                        &summary.daily_totals,
+                        &self.rates,
+                        date,
+                    );
+                }
+                let totals = self.range.days(&data.daily_totals(&self.day_filter));
+                let total: f64 = totals.values().sum();
+                // Costed from the same days as the total, so a day filter narrows both.
+                let cost = self.rates.days_cost(&totals);
+                let day_count = totals.len();
+                let partial_day = self
+                    .summary
+                    .as_ref()
+                    .and_then(|summary| summary.partial_day)
+                    .filter(|day| totals.contains_key(day));
+                ui.label(format!(
+                    "Total: {} over {} days{}",
+                    number_format.kilowatt_hours(total),
+                    totals.len(),
+                    if partial_day.is_some() {
+                        " (last day partial)"
+                    } else {
+                        ""
+                    }
+                ));
+                let averaged = match &self.summary {
+                    Some(summary) => summary.complete_days(&totals, self.exclude_partial_day),
+                    None => totals,
+                };
+                if !averaged.is_empty() {
+                    let averaged_total: f64 = averaged.values().sum();
+                    ui.label(format!(
+                        "Average: {}/day",
+                        number_format.kilowatt_hours(averaged_total / averaged.len() as f64)
+                    ));
+                }
+                if !self.display.tags.delimiter.is_empty() {
+                    ui.collapsing("Usage by tag", |ui| {
+                        let totals = tags::tag_totals(
+                            data,
+                            &self.display.tags.delimiter,
+                            self.range,
+                            &self.day_filter,
+                        );
+                        tags::tag_breakdown_ui(ui, &totals, total, number_format);
+                    });
+                }
+                display::cost_label(
+                    ui,
+                    cost,
+                    format!("Estimated cost of range: {}", number_format.cost(cost)),
+                );
+                if let Some(emissions) = self.daily_emissions.as_ref().filter(|_| day_count > 0) {
+                    let range_emissions = emissions.total(self.range.start, |day| {
+                        day <= self.range.end && self.day_filter.includes(day)
+                    });
+                    ui.label(format!(
+                        "Estimated emissions of range: {} ({}/day)",
+                        emissions::kilograms(number_format, range_emissions),
+                        emissions::kilograms(number_format, range_emissions / day_count as f64)
+                    ));
+                }
+
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.view, View::Day, "Day");
+                    ui.selectable_value(&mut self.view, View::CostRate, "Cost per hour");
+                    ui.selectable_value(&mut self.view, View::Cycle, "Billing cycle");
+                    ui.selectable_value(&mut self.view, View::Months, "Months");
+                    ui.selectable_value(&mut self.view, View::DailyNet, "Net by day");
+                    ui.selectable_value(&mut self.view, View::Trend, "Trend");
+                    ui.selectable_value(&mut self.view, View::Timeline, "Timeline");
+                    ui.selectable_value(&mut self.view, View::Emissions, "Emissions");
+                    ui.selectable_value(&mut self.view, View::Calendar, "Calendar");
+                    ui.selectable_value(&mut self.view, View::Weekday, "Same weekday");
+                    ui.selectable_value(&mut self.view, View::WeekHours, "Week × hour");
+                    ui.selectable_value(&mut self.view, View::Days, "Days");
+                    ui.selectable_value(&mut self.view, View::EntryCounts, "Entries per day");
+                });
+
+                match self.view {
+                    View::Months => {
+                        if let Some(clicked) = overview::month_grid_ui(ui, data, &self.day_filter) {
+                            date = clicked;
+                            self.view = View::Day;
+                        }
+                    }
+                    View::Calendar => {
+                        if calendar::calendar_ui(
+                            ui,
+                            data,
+                            &self.day_filter,
+                            number_format,
+                            &self.bookmarks,
+                            &mut date,
+                        ) {
+                            self.view = View::Day;
+                        }
+                    }
+                    View::DailyNet => {
+                        if let Some(clicked) =
+                            overview::daily_net_ui(ui, &data.daily_net(self.range), &self.display)
+                        {
+                            date = clicked;
+                            self.view = View::Day;
+                        }
+                    }
+                    View::Emissions => {
+                        if let Some(clicked) = self.daily_emissions.as_ref().and_then(|emissions| {
+                            emissions::emissions_ui(
+                                ui,
+                                emissions,
+                                &self.day_filter,
+                                self.range,
+                                &self.display,
+                            )
+                        }) {
+                            date = clicked;
+                            self.view = View::Day;
+                        }
+                    }
+                    View::EntryCounts => {
+                        if let Some(clicked) =
+                            overview::entries_per_day_ui(ui, &data.entries_per_day(), &self.display)
+                        {
+                            date = clicked;
+                            self.view = View::Day;
+                        }
+                    }
+                    View::CostRate => cost_rate::cost_rate_ui(
+                        ui,
+                        data,
+                        self.entry_costs.as_ref().map_or(&[], |costs| &costs.costs),
+                        &self.display,
+                        date,
+                    ),
+                    View::Days => {
+                        let mut days = data.hourly_by_day(&self.day_filter);
+                        days.retain(|day, _| (self.range.start..=self.range.end).contains(day));
+                        if let Some(clicked) = table::days_table_ui(ui, &days, &self.display) {
+                            date = clicked;
+                            self.view = View::Day;
+                        }
+                    }
+                    View::Cycle => {
+                        cycle::cycle_ui(ui, data, &self.rates, &self.display, date);
+                    }
+                    View::Timeline => {
+                        timeline::timeline_ui(ui, data, &self.day_filter, self.range, &self.display)
+                    }
+                    View::Trend => trend::trend_ui(
+                        ui,
+                        data,
+                        &self.day_filter,
+                        self.range,
+                        &self.display,
+                        &mut self.trend_settings,
+                        &mut self.trend,
+                    ),
+                    View::WeekHours => heatmap::week_hours_ui(
+                        ui,
+                        &data.weekday_hour_averages(&self.day_filter),
+                        &self.display,
+                    ),
+                    View::Weekday => weekday::weekday_ui(
+                        ui,
+                        data,
+                        &self.day_filter,
+                        self.range,
+                        &self.display,
+                        &mut self.compared_weekday,
+                    ),
+                    View::Day => {
+                        if let Some(summary) = &self.summary {
+                            if let Some(clicked) = density::density_strip_ui(
+                                ui,
+                                summary.first_day,
+                                summary.last_day,
+                                &summary.daily_totals,
+                                &self.bookmarks,
+                                self.display.date_format,
+                                date,
+                            ) {
+                                date = clicked;
+                            }
+                        }
+                        if self
+                            .summary
+                            .as_ref()
+                            .is_some_and(|summary| summary.partial_day == Some(date))
+                        {
+                            ui.weak("Partial day: the file ends before midnight");
+                        }
+                        if let Err(error) = day_view::day_view_ui(
+                            ui,
+                            data,
+                            &self.comparisons,
+                            &self.day_filter,
+                            self.entry_costs.as_ref().map_or(&[], |costs| &costs.costs),
+                            &mut self.display,
+                            date,
+                        ) {
+                            self.error_causes = error_causes(&error);
+                            self.error = Some(error.to_string());
+                        }
+                    }
+                }
+
+                self.year = date.year() as u16;
+                self.month = date.month() as u8;
+                self.day = date.day() as u8;
+            }
+        });
+    }
+}
+
+fn powered_by_egui_and_eframe(ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        ui.label("Powered by ");
+        ui.hyperlink_to("egui", "https://github.com/emilk/egui");
+        ui.label(" and ");
+        ui.hyperlink_to(
+            "eframe",
+            "https://github.com/emilk/egui/tree/master/crates/eframe",
+        );
+        ui.label(".");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> UsageEntry {
+        UsageEntry::parse(line, false).unwrap()
+    }
+
+    #[test]
+    fn midnight_after_a_late_start_ends_the_day() {
+        let entry = entry("Electric usage,1/15/2024,11:00 PM,12:00 AM,0.51,kWh,");
+        assert_eq!(entry.interval_start_hour, 23);
+        assert_eq!(
+            (entry.interval_end_hour, entry.interval_end_minute),
+            (24, 0)
+        );
+        assert_eq!(entry.duration_hours(), 1.0);
+    }
+
+    #[test]
+    fn midnight_as_a_start_begins_the_day() {
+        let entry = entry("Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,");
+        assert_eq!(entry.interval_start_hour, 0);
+        assert_eq!(entry.interval_end_hour, 1);
+    }
+
+    #[test]
+    fn noon_is_twelve() {
+        let entry = entry("Electric usage,1/15/2024,12:00 PM,12:30 PM,0.42,kWh,");
+        assert_eq!(
+            (entry.interval_start_hour, entry.interval_end_hour),
+            (12, 12)
+        );
+        assert_eq!(entry.interval_end_minute, 30);
+    }
+
+    #[test]
+    fn start_times_with_and_without_leading_zeros() {
+        for (time, hour, minute) in [
+            ("8:00 AM", 8, 0),
+            ("08:00 AM", 8, 0),
+            ("9:30 am", 9, 30),
+            ("12:00 AM", 0, 0),
+            ("12:15 AM", 0, 15),
+            ("12:00 PM", 12, 0),
+            ("1:00 PM", 13, 0),
+            ("01:45 pm", 13, 45),
+            ("11:00 PM", 23, 0),
+        ] {
+            let entry = entry(&format!(
+                "Electric usage,1/15/2024,{time},11:59 PM,0.1,kWh,"
+            ));
+            assert_eq!(
+                (entry.interval_start_hour, entry.interval_start_minute),
+                (hour, minute),
+                "{time}"
+            );
+        }
+    }
+
+    #[test]
+    fn usage_and_unit_in_one_column() {
+        let combined = entry("Electric usage,1/15/2024,1:00 AM,2:00 AM,0.42 kWh,");
+        assert_eq!(combined.kilowatt_hours, 0.42);
+        assert_eq!(combined.note(), "");
+        let quoted = entry("Electric usage,1/15/2024,1:00 AM,2:00 AM,\"0.42 kWh\",Estimated");
+        assert_eq!(quoted.kilowatt_hours, 0.42);
+        assert!(quoted.is_estimated());
+        let separate = entry("Electric usage,1/15/2024,1:00 AM,2:00 AM,0.42,kWh,");
+        assert_eq!(separate.kilowatt_hours, 0.42);
+        let line = "Electric usage,1/15/2024,1:00 AM,2:00 AM,0.42 MWh,";
+        assert!(UsageEntry::parse(line, false).is_err());
+    }
+
+    #[test]
+    fn start_times_outside_a_12_hour_clock_fail() {
+        for time in ["0:00 AM", "13:00 PM", "8:60 AM", "8:00", "8 AM", "8:00 XM"] {
+            let line = format!("Electric usage,1/15/2024,{time},11:59 PM,0.1,kWh,");
+            assert!(UsageEntry::parse(&line, false).is_err(), "{time}");
+        }
+    }
+
+    #[test]
+    fn a_windows_1252_address_is_decoded() {
+        let mut bytes = b"Caf\xe9 \x96 12\xb0 North St\n\n".to_vec();
+        bytes.extend_from_slice(HEADER.as_bytes());
+        bytes.extend_from_slice(b"\nElectric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,\n");
+        let (data, skipped) = UsageData::parse_reader(bytes.as_slice(), options(false)).unwrap();
+        assert_eq!(data.address, "Caf\u{e9} \u{2013} 12\u{b0} North St");
+        assert_eq!(data.entries.len(), 1);
+        assert!(skipped.is_empty());
+        assert_eq!(decode_text("12°".as_bytes()).unwrap(), "12°");
+    }
+
+    #[test]
+    fn a_missing_note_is_only_accepted_leniently() {
+        let line = "Electric usage,1/15/2024,1:00 AM,2:00 AM,0.42,kWh";
+        let Err(error) = UsageEntry::parse(line, false) else {
+            panic!("a strict parse needs the note column");
+        };
+        assert!(error.to_string().contains("Expected final entry for note"));
+        let entry = UsageEntry::parse(line, true).unwrap();
+        assert_eq!(entry.kilowatt_hours, 0.42);
+        assert_eq!(entry.note, None);
+    }
+
+    #[test]
+    fn a_trailing_totals_row_is_skipped_with_a_warning() {
+        let input = format!("Test address\n\n{HEADER}\n{SAMPLE_ROWS}Total,,,,2.56,kWh,\n");
+        let options = ParseOptions {
+            lenient: false,
+            skip_totals_rows: true,
+        };
+        let (data, skipped) = UsageData::parse(&input, options).unwrap();
+        assert_eq!(data.entries.len(), 4);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].line, 8);
+        assert!(skipped[0].message.contains("totals row"));
+
+        let options = ParseOptions {
+            skip_totals_rows: false,
+            ..options
+        };
+        assert!(UsageData::parse(&input, options).is_err());
+        assert!(!is_totals_row(SAMPLE_ROWS.lines().next().unwrap()));
+    }
+
+    #[test]
+    fn a_trailing_comma_after_the_note_is_only_ignored_leniently() {
+        let line = "Electric usage,1/15/2024,7:00 AM,8:00 AM,1.25,kWh,Estimated,";
+        let Err(error) = UsageEntry::parse(line, false) else {
+            panic!("a strict parse rejects the extra column");
+        };
+        assert!(error.to_string().contains("Extra entries"));
+        let entry = UsageEntry::parse(line, true).unwrap();
+        assert_eq!(entry.note(), "Estimated");
+        let empty_note =
+            UsageEntry::parse("Electric usage,1/15/2024,7:00 AM,8:00 AM,1.25,kWh,,", true);
+        assert_eq!(empty_note.unwrap().note, None);
+        // Only a single empty field is a trailing comma; more is still extra data.
+        for line in [
+            "Electric usage,1/15/2024,7:00 AM,8:00 AM,1.25,kWh,Estimated,,",
+            "Electric usage,1/15/2024,7:00 AM,8:00 AM,1.25,kWh,Estimated,meter 2",
+        ] {
+            assert!(UsageEntry::parse(line, true).is_err(), "{line}");
+        }
+    }
+
+    fn options(lenient: bool) -> ParseOptions {
+        ParseOptions {
+            lenient,
+            skip_totals_rows: false,
+        }
+    }
+
+    /// Two good rows around two bad ones, on lines 5 and 6 of the file.
+    fn with_bad_rows() -> String {
+        format!(
+            "Test address\n\n{HEADER}\n\
+Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,
+Electric usage,1/15/2024,1:00 AM,2:00 AM,lots,kWh,
+Electric usage,2/30/2024,2:00 AM,3:00 AM,0.30,kWh,
+Electric usage,1/15/2024,3:00 AM,4:00 AM,0.25,kWh,
+"
+        )
+    }
+
+    #[test]
+    fn strict_parsing_fails_on_the_first_bad_row() {
+        let Err(error) = UsageData::parse(&with_bad_rows(), options(false)) else {
+            panic!("a bad row should fail a strict parse");
+        };
+        assert_eq!(error.downcast_ref::<OnLine>().map(|line| line.0), Some(5));
+        assert!(format!("{error:#}").contains("Invalid usage \"lots\""));
+    }
+
+    #[test]
+    fn lenient_parsing_skips_bad_rows_and_reports_them() {
+        let (data, skipped) = UsageData::parse(&with_bad_rows(), options(true)).unwrap();
+        assert_eq!(data.entries.len(), 2);
+        assert_eq!(data.entries[1].kilowatt_hours, 0.25);
+        let lines = skipped.iter().map(|line| line.line).collect::<Vec<_>>();
+        assert_eq!(lines, [5, 6]);
+        assert!(skipped[1].message.contains("Invalid date"));
+    }
+
+    const DRIFTING: &str = "\
+Electric usage,1/15/2024,8:59 AM,9:58 AM,0.42,kWh,
+Electric usage,1/15/2024,11:31 PM,12:00 AM,0.42,kWh,
+";
+
+    #[test]
+    fn snapping_moves_starts_to_the_nearest_hour() {
+        let mut data = UsageData::from_rows(DRIFTING);
+        data.snap_hour_buckets(true);
+        let buckets = data.entries.iter().map(UsageEntry::hour_bucket);
+        // The day's last hour doesn't round into the next day.
+        assert_eq!(buckets.collect::<Vec<_>>(), [9, 23]);
+        let entry = &data.entries[0];
+        assert_eq!(
+            (entry.interval_start_hour, entry.interval_start_minute),
+            (8, 59)
+        );
+    }
+
+    #[test]
+    fn toggling_snapping_drops_the_trend() {
+        let data = UsageData::from_rows(DRIFTING);
+        let mut app = TemplateApp {
+            trend: Some(trend::Trend::new(
+                &data,
+                &DayFilter::default(),
+                DateRange::default(),
+                trend::TrendSettings::default(),
+            )),
+            data: Some(data),
+            ..Default::default()
+        };
+        app.set_snap_hour_buckets(true);
+        assert!(app.trend.is_none());
+        assert_eq!(app.data.unwrap().entries[0].hour_bucket, Some(9));
+    }
+
+    #[test]
+    fn without_snapping_entries_stay_in_the_hour_they_start() {
+        let mut data = UsageData::from_rows(DRIFTING);
+        assert_eq!(data.entries[0].hour_bucket(), 8);
+        data.snap_hour_buckets(true);
+        data.snap_hour_buckets(false);
+        let buckets = data.entries.iter().map(UsageEntry::hour_bucket);
+        assert_eq!(buckets.collect::<Vec<_>>(), [8, 23]);
+    }
+
+    #[test]
+    fn a_file_that_cant_be_read_is_reported() {
+        let path = std::env::temp_dir().join("plot-electricity-no-such-file.csv");
+        let mut app = TemplateApp::default();
+        assert!(app.load_from_path(&path, true, Target::Main).is_none());
+        let error = app.error.unwrap();
+        assert!(error.contains("Could not open"), "{error}");
+        assert!(
+            error.contains("plot-electricity-no-such-file.csv"),
+            "{error}"
+        );
+        assert!(!app.error_causes.is_empty());
+    }
+
+    #[test]
+    fn a_cached_file_is_normalized_per_the_current_setting() {
+        let path = std::env::temp_dir().join("plot-electricity-cached-out-of-order.csv");
+        let rows = "\
+Electric usage,1/15/2024,1:00 AM,2:00 AM,0.5,kWh,
+Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,
+";
+        std::fs::write(&path, format!("Test address\n\n{HEADER}\n{rows}")).unwrap();
+        let mut app = TemplateApp::default();
+        let first_hour = |data: UsageData| data.entries[0].interval_start_hour;
+
+        app.normalize_on_load = true;
+        let sorted = app
+            .load_from_path(&path, false, Target::Main)
+            .map(first_hour);
+        app.normalize_on_load = false;
+        let cached = app
+            .load_from_path(&path, true, Target::Main)
+            .map(first_hour);
+        let stats = app.load_stats.take().unwrap();
+        std::fs::remove_file(cache::cache_path(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(sorted, Some(0));
+        assert_eq!(cached, Some(1));
+        assert!(stats.from_cache);
+        assert!(!stats.normalized.reordered);
+    }
+
+    #[test]
+    fn a_retried_comparison_is_added_as_a_comparison() {
+        let path = std::env::temp_dir().join("plot-electricity-day-first-comparison.csv");
+        let rows = "Electric usage,15/1/2024,12:00 AM,1:00 AM,0.42,kWh,\n";
+        std::fs::write(&path, format!("Test address\n\n{HEADER}\n{rows}")).unwrap();
+        let mut app = TemplateApp::default();
+        app.show_file(
+            std::path::PathBuf::from("main.csv"),
+            UsageData::from_rows(SAMPLE_ROWS),
+        );
+
+        assert!(app
+            .load_from_path(&path, false, Target::Comparison)
+            .is_none());
+        assert!(app.load_stats.is_none());
+        app.retry_parse(raw_input::Retry::DayFirst);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(app.error.is_none());
+        assert!(app.failed_input.is_none());
+        assert!(app.load_stats.is_none());
+        assert_eq!(app.data_path, Some(std::path::PathBuf::from("main.csv")));
+        assert_eq!(app.comparisons.len(), 1);
+        assert_eq!(app.comparisons[0].data.entries[0].date, (2024, 1, 15));
+    }
+
+    #[test]
+    fn a_retry_with_nowhere_to_open_keeps_the_error() {
+        let mut app = TemplateApp {
+            error: Some("Bad date".to_owned()),
+            failed_input: Some(FailedInput {
+                path: None,
+                target: Target::Main,
+                text: format!("Test address\n\n{HEADER}\n"),
+                line: None,
+            }),
+            ..Default::default()
+        };
+        app.retry_parse(raw_input::Retry::DayFirst);
+        assert_eq!(app.error.as_deref(), Some("Bad date"));
+        assert!(app.failed_input.is_some());
+    }
+
+    #[test]
+    fn energy_adds_up_while_power_is_weighted_by_interval_length() {
+        let data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,1:00 AM,2:00 AM,1.0,kWh,
+Electric usage,1/15/2024,2:00 AM,2:15 AM,0.5,kWh,
+",
+        );
+        assert!((data.total_energy() - 1.5).abs() < 1e-9);
+        // 1.5 kWh over an hour and a quarter, not the 1.5 kW mean of 1 kW and 2 kW.
+        assert!((data.average_power().unwrap() - 1.2).abs() < 1e-9);
+        assert_eq!(UsageData::from_rows("").average_power(), None);
+    }
+
+    #[test]
+    fn excluded_entries_leave_the_daily_total() {
+        let mut data = UsageData::from_rows(SAMPLE_ROWS);
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let total = |data: &UsageData| data.daily_totals(&DayFilter::default())[&day];
+        assert!((total(&data) - 2.56).abs() < 1e-9);
+        data.toggle_excluded((day, 7, 0));
+        assert!(data.entries[2].excluded);
+        assert!((total(&data) - 1.31).abs() < 1e-9);
+        assert_eq!(data.entries.len(), 4);
+        data.toggle_excluded((day, 7, 0));
+        assert!((total(&data) - 2.56).abs() < 1e-9);
+    }
+
+    #[test]
+    fn daily_net_is_imports_less_exports() {
+        let mut data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,8:00 AM,9:00 AM,1.5,kWh,
+Electric usage,1/15/2024,12:00 PM,1:00 PM,-4.0,kWh,
+Electric usage,1/15/2024,6:00 PM,7:00 PM,1.0,kWh,
+Electric usage,1/16/2024,8:00 AM,9:00 AM,2.0,kWh,
+Electric usage,1/16/2024,12:00 PM,1:00 PM,-0.5,kWh,
+",
+        );
+        let day = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let range = DateRange::month(day(1));
+        assert_eq!(data.daily_net(range), [(day(15), -1.5), (day(16), 1.5)]);
+        data.entries[1].excluded = true;
+        assert_eq!(data.daily_net(range), [(day(15), 2.5), (day(16), 1.5)]);
+        let range = DateRange {
+            start: day(16),
+            end: day(31),
+        };
+        assert_eq!(data.daily_net(range), [(day(16), 1.5)]);
+    }
+
+    /// One hour-long entry at 8 AM on each of `days`, in order, of January 2024.
+    fn one_entry_per_day(days: impl IntoIterator<Item = u32>) -> UsageData {
+        let rows = days
+            .into_iter()
+            .map(|day| format!("Electric usage,1/{day}/2024,8:00 AM,9:00 AM,1.0,kWh,\n"))
+            .collect::<String>();
+        UsageData::from_rows(&rows)
+    }
+
+    #[test]
+    fn trimming_keeps_the_last_days() {
+        let mut data = one_entry_per_day(1..=10);
+        let day = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let cutoff = data.retention_cutoff(3).unwrap();
+        assert_eq!(cutoff, day(8));
+        assert_eq!(data.trim_before(cutoff), 7);
+        let days = data.entries.iter().map(UsageEntry::day).collect::<Vec<_>>();
+        assert_eq!(days, [day(8), day(9), day(10)]);
+        assert_eq!(data.trim_before(cutoff), 0);
+        // Keeping no days still keeps the latest one.
+        assert_eq!(data.retention_cutoff(0), Some(day(10)));
+        assert_eq!(UsageData::from_rows("").retention_cutoff(3), None);
+    }
+
+    #[test]
+    fn normalizing_sorts_and_drops_exact_duplicates() {
+        let mut data = one_entry_per_day([3, 1, 2, 1]);
+        // Same start as a kept entry but a different reading, so it isn't a duplicate.
+        data.entries.push(
+            UsageEntry::parse("Electric usage,1/2/2024,8:00 AM,9:00 AM,1.5,kWh,", false).unwrap(),
+        );
+        let normalized = data.normalize();
+        assert!(normalized.reordered);
+        assert_eq!(normalized.duplicates_removed, 1);
+        let days = data
+            .entries
+            .iter()
+            .map(|entry| (entry.date, entry.kilowatt_hours));
+        assert_eq!(
+            days.collect::<Vec<_>>(),
+            [
+                ((2024, 1, 1), 1.0),
+                ((2024, 1, 2), 1.0),
+                ((2024, 1, 2), 1.5),
+                ((2024, 1, 3), 1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizing_clean_data_reports_nothing() {
+        let mut data = one_entry_per_day(1..=3);
+        let normalized = data.normalize();
+        assert!(!normalized.reordered);
+        assert_eq!(normalized.duplicates_removed, 0);
+        assert_eq!(data.entries.len(), 3);
+    }
+
+    #[test]
+    fn the_sample_file_parses_as_a_whole_day() {
+        let options = ParseOptions {
+            lenient: false,
+            skip_totals_rows: true,
+        };
+        let (data, skipped) = UsageData::parse(&sample_file(), options).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(data.address, "123 Example St, Springfield");
+        assert_eq!(data.entries.len(), 4);
+        assert!(data
+            .entries
+            .iter()
+            .all(|entry| entry.duration_hours() == 1.0));
+        assert_eq!(data.partial_day(), None);
+        assert!(data.entries[2].is_estimated());
+    }
+
+    /// An entry as saved state holds it, with `note` as given.
+    fn saved_entry(note: &str) -> String {
+        format!(
+            "(date: (2024, 1, 15), interval_start_hour: 7, interval_end_hour: 8, \
+             kilowatt_hours: 1.25, note: {note})"
+        )
+    }
+
+    #[test]
+    fn notes_load_whether_saved_as_strings_or_options() {
+        let note = |saved: &str| {
+            ron::from_str::<UsageEntry>(&saved_entry(saved))
+                .unwrap()
+                .note
+        };
+        assert_eq!(note(r#""Estimated""#).as_deref(), Some("Estimated"));
+        assert_eq!(note(r#""""#), None);
+        assert_eq!(note(r#"Some("Estimated")"#).as_deref(), Some("Estimated"));
+        assert_eq!(note("None"), None);
+    }
+
+    #[test]
+    fn notes_survive_saving_and_the_cache() {
+        let data = UsageData::from_rows(SAMPLE_ROWS);
+        let saved = ron::to_string(&data).unwrap();
+        let loaded = ron::from_str::<UsageData>(&saved).unwrap();
+        assert!(loaded.entries == data.entries);
+        let cached = bincode::deserialize::<UsageData>(&bincode::serialize(&data).unwrap());
+        assert!(cached.unwrap().entries == data.entries);
+    }
 }
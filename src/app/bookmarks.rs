@@ -0,0 +1,68 @@
+//! Days saved with a short label, like "party" or "heatwave", to come back to later.
+
+use chrono::NaiveDate;
+
+use super::display::DateFormat;
+
+/// Bookmarks in the order they were added; a day has at most one.
+pub type Bookmarks = Vec<(NaiveDate, String)>;
+
+/// The label of `day`'s bookmark, if it has one.
+pub fn label(bookmarks: &[(NaiveDate, String)], day: NaiveDate) -> Option<&str> {
+    bookmarks
+        .iter()
+        .find(|(bookmarked, _)| *bookmarked == day)
+        .map(|(_, label)| label.as_str())
+}
+
+/// The contents of the Bookmarks menu: bookmarking `selected` under `new_label`, and every
+/// bookmark to jump to or remove. Returns the day of a bookmark that was clicked.
+pub fn bookmarks_menu_ui(
+    ui: &mut egui::Ui,
+    bookmarks: &mut Bookmarks,
+    selected: NaiveDate,
+    new_label: &mut String,
+    date_format: DateFormat,
+) -> Option<NaiveDate> {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(new_label)
+                .hint_text("Label")
+                .desired_width(120.0),
+        );
+        let label = new_label.trim();
+        if ui
+            .add_enabled(!label.is_empty(), egui::Button::new("Bookmark this day"))
+            .clicked()
+        {
+            bookmarks.retain(|(day, _)| *day != selected);
+            bookmarks.push((selected, label.to_owned()));
+            new_label.clear();
+        }
+    });
+
+    if bookmarks.is_empty() {
+        ui.weak("No bookmarks yet");
+        return None;
+    }
+    ui.separator();
+    let mut clicked = None;
+    let mut removed = None;
+    for (i, (day, label)) in bookmarks.iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .button(format!("{}  {label}", date_format.format(*day)))
+                .clicked()
+            {
+                clicked = Some(*day);
+            }
+            if ui.small_button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        bookmarks.remove(i);
+    }
+    clicked
+}
@@ -0,0 +1,84 @@
+//! A monthly usage budget, and how the current billing cycle is tracking against it.
+
+use std::collections::BTreeMap;
+
+use chrono::{Months, NaiveDate};
+
+use super::display::NumberFormat;
+use super::rates::RateSchedule;
+
+/// How far into its budget the billing cycle containing the latest data is.
+pub struct CycleProgress {
+    pub used: f64,
+    /// `used` extrapolated to the whole cycle at the cycle's average daily rate so far.
+    pub projected: f64,
+    pub budget: f64,
+}
+
+impl CycleProgress {
+    /// Works out the progress of the cycle containing the last day in `daily_totals`.
+    pub fn new(
+        daily_totals: &BTreeMap<NaiveDate, f64>,
+        rates: &RateSchedule,
+        budget: f64,
+    ) -> Option<Self> {
+        let (&last_day, _) = daily_totals.last_key_value()?;
+        let start = rates.cycle_start(last_day);
+        let end = start + Months::new(1);
+        let used: f64 = daily_totals
+            .range(start..=last_day)
+            .map(|(_, total)| total)
+            .sum();
+        let elapsed = (last_day - start).num_days() + 1;
+        let length = (end - start).num_days();
+        Some(Self {
+            used,
+            projected: used / elapsed as f64 * length as f64,
+            budget,
+        })
+    }
+}
+
+pub fn budget_ui(ui: &mut egui::Ui, budget: &mut Option<f64>) {
+    ui.horizontal(|ui| {
+        let mut enabled = budget.is_some();
+        ui.checkbox(&mut enabled, "Monthly budget");
+        match (enabled, budget.as_mut()) {
+            (true, Some(kilowatt_hours)) => {
+                ui.add(
+                    egui::DragValue::new(kilowatt_hours)
+                        .range(1.0..=f64::MAX)
+                        .suffix(" kWh"),
+                );
+            }
+            (true, None) => *budget = Some(500.0),
+            (false, _) => *budget = None,
+        }
+    });
+}
+
+pub fn progress_ui(ui: &mut egui::Ui, progress: &CycleProgress, number_format: NumberFormat) {
+    ui.add(
+        egui::ProgressBar::new((progress.used / progress.budget) as f32)
+            .text(format!(
+                "{} of {} this cycle",
+                number_format.kilowatt_hours(progress.used),
+                number_format.kilowatt_hours(progress.budget)
+            ))
+            .desired_width(300.0),
+    );
+    if progress.projected <= progress.budget {
+        ui.label(format!(
+            "You're on track: projected {} by the end of the cycle",
+            number_format.kilowatt_hours(progress.projected)
+        ));
+    } else {
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!(
+                "Projected to go over by {}",
+                number_format.kilowatt_hours(progress.projected - progress.budget)
+            ),
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! A binary copy of a parsed file, kept next to it so big files don't have to be parsed again.
+//!
+//! The source file stays the source of truth: the cache records the size and modification time
+//! it was built from, and the options it was parsed with, and is ignored once any of them
+//! changes. It holds the data as parsed, before anything a load does to it afterwards.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Context;
+
+use super::{ParseOptions, UsageData};
+
+/// Bumped whenever [`UsageData`]'s layout changes, so caches from older builds are rebuilt
+/// rather than misread.
+const CACHE_FORMAT: u32 = 5;
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Cache<D> {
+    format: u32,
+    source_modified: SystemTime,
+    source_len: u64,
+    options: ParseOptions,
+    data: D,
+}
+
+pub fn cache_path(source: &Path) -> PathBuf {
+    let mut path = source.as_os_str().to_owned();
+    path.push(".cache");
+    path.into()
+}
+
+fn source_stamp(source: &Path) -> anyhow::Result<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(source)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+/// The cached data for `source`, if there is a cache, it's still up to date, and it was parsed
+/// with `options`.
+pub fn load(source: &Path, options: ParseOptions) -> Option<UsageData> {
+    let (modified, len) = source_stamp(source).ok()?;
+    let bytes = std::fs::read(cache_path(source)).ok()?;
+    let cache: Cache<UsageData> = bincode::deserialize(&bytes).ok()?;
+    (cache.format == CACHE_FORMAT
+        && cache.source_modified == modified
+        && cache.source_len == len
+        && cache.options == options)
+        .then_some(cache.data)
+}
+
+/// Writes a cache of `data`, parsed from `source` with `options`.
+pub fn store(source: &Path, options: ParseOptions, data: &UsageData) -> anyhow::Result<()> {
+    let (source_modified, source_len) = source_stamp(source).with_context(|| {
+        format!(
+            "Could not read the modification time of {}",
+            source.display()
+        )
+    })?;
+    let bytes = bincode::serialize(&Cache {
+        format: CACHE_FORMAT,
+        source_modified,
+        source_len,
+        options,
+        data,
+    })?;
+    let path = cache_path(source);
+    std::fs::write(&path, bytes).with_context(|| format!("Could not write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_cache_is_only_used_with_the_options_it_was_parsed_with() {
+        let source = std::env::temp_dir().join("plot-electricity-cache-options.csv");
+        std::fs::write(&source, "").unwrap();
+        let strict = ParseOptions {
+            lenient: false,
+            skip_totals_rows: false,
+        };
+        let lenient = ParseOptions {
+            lenient: true,
+            ..strict
+        };
+        let data = UsageData::from_rows("Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,\n");
+        store(&source, strict, &data).unwrap();
+        let cached = load(&source, strict).map(|data| data.entries.len());
+        let missed = load(&source, lenient).is_none();
+        std::fs::remove_file(cache_path(&source)).unwrap();
+        std::fs::remove_file(&source).unwrap();
+        assert_eq!(cached, Some(1));
+        assert!(missed);
+    }
+}
@@ -0,0 +1,93 @@
+//! A month laid out as a calendar, each day shaded by its total, for spotting heavy days.
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+use super::bookmarks;
+use super::display::NumberFormat;
+use super::{DayFilter, UsageData};
+
+const CELL_SIZE: f32 = 36.0;
+const HEAVIEST: egui::Color32 = egui::Color32::from_rgb(40, 160, 70);
+
+/// Draws the month containing `date`, with buttons to move between months and a star on
+/// bookmarked days. Returns whether a day was clicked, in which case `date` is that day.
+pub fn calendar_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    filter: &DayFilter,
+    number_format: NumberFormat,
+    bookmarks: &[(NaiveDate, String)],
+    date: &mut NaiveDate,
+) -> bool {
+    let first = date.with_day(1).unwrap();
+    let next_month = first + Months::new(1);
+
+    ui.horizontal(|ui| {
+        if ui.button("◀").clicked() {
+            *date = first - Months::new(1);
+        }
+        ui.strong(first.format("%B %Y").to_string());
+        if ui.button("▶").clicked() {
+            *date = next_month;
+        }
+    });
+
+    let daily_totals = data.daily_totals(filter);
+    let month_totals = daily_totals.range(first..next_month);
+    let heaviest = month_totals
+        .clone()
+        .map(|(_, &total)| total)
+        .fold(0.0, f64::max);
+
+    let mut clicked = false;
+    egui::Grid::new("calendar")
+        .spacing([4.0, 4.0])
+        .show(ui, |ui| {
+            for weekday in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+                ui.label(weekday);
+            }
+            ui.end_row();
+
+            // Blank cells up to the weekday the month starts on.
+            for _ in 0..first.weekday().num_days_from_monday() {
+                ui.label("");
+            }
+            let mut day = first;
+            while day < next_month {
+                let total = daily_totals.get(&day).copied();
+                let fill = match total {
+                    Some(total) if heaviest > 0.0 => ui
+                        .visuals()
+                        .faint_bg_color
+                        .lerp_to_gamma(HEAVIEST, (total / heaviest) as f32),
+                    _ => ui.visuals().faint_bg_color,
+                };
+                let bookmark = bookmarks::label(bookmarks, day);
+                let text = match bookmark {
+                    Some(_) => format!("{}★", day.day()),
+                    None => day.day().to_string(),
+                };
+                let button = egui::Button::new(text)
+                    .fill(fill)
+                    .selected(day == *date)
+                    .min_size(egui::vec2(CELL_SIZE, CELL_SIZE));
+                let mut hover = match total {
+                    Some(total) => number_format.kilowatt_hours(total),
+                    None => "No data".to_owned(),
+                };
+                if let Some(label) = bookmark {
+                    hover = format!("{label}\n{hover}");
+                }
+                let response = ui.add(button).on_hover_text(hover);
+                if response.clicked() {
+                    *date = day;
+                    clicked = true;
+                }
+                if day.weekday().num_days_from_monday() == 6 {
+                    ui.end_row();
+                }
+                day = day + Days::new(1);
+            }
+        });
+    clicked
+}
@@ -0,0 +1,233 @@
+//! Extra files loaded alongside the main one and drawn over it for comparison.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::display::{DateFormat, NumberFormat};
+use super::range::DateRange;
+use super::UsageData;
+
+/// Colors handed out to comparison files in turn, chosen to stand out against the default bars.
+const PALETTE: [[u8; 4]; 4] = [
+    [230, 120, 40, 255],
+    [60, 170, 90, 255],
+    [200, 60, 160, 255],
+    [220, 190, 40, 255],
+];
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct Dataset {
+    pub label: String,
+    /// Unmultiplied sRGBA, since `egui::Color32` isn't the form we want to persist.
+    pub color: [u8; 4],
+    pub data: UsageData,
+}
+
+impl Dataset {
+    /// Labels a newly loaded file, picking the next color in [`PALETTE`].
+    pub fn new(label: String, data: UsageData, existing: usize) -> Self {
+        Self {
+            label,
+            color: PALETTE[existing % PALETTE.len()],
+            data,
+        }
+    }
+
+    pub fn color32(&self) -> egui::Color32 {
+        let [r, g, b, a] = self.color;
+        egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+}
+
+/// How two files differ, such as a re-download and the original it should match.
+pub struct DatasetDiff {
+    pub first_total: f64,
+    pub second_total: f64,
+    /// The days both files span, if their spans meet at all.
+    pub overlap: Option<DateRange>,
+    /// Intervals, by day and start time, that only one of the files has.
+    pub only_in_first: usize,
+    pub only_in_second: usize,
+    /// Intervals both files have but with different usage.
+    pub changed: usize,
+}
+
+/// Compares two files interval by interval.
+pub fn diff(first: &UsageData, second: &UsageData) -> DatasetDiff {
+    let intervals = |data: &UsageData| {
+        data.entries
+            .iter()
+            .map(|entry| {
+                (
+                    (
+                        entry.day(),
+                        entry.interval_start_hour,
+                        entry.interval_start_minute,
+                    ),
+                    entry.kilowatt_hours,
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let first_intervals = intervals(first);
+    let second_intervals = intervals(second);
+    let span = |intervals: &BTreeMap<(NaiveDate, u8, u8), f64>| {
+        let (&(start, ..), _) = intervals.first_key_value()?;
+        let (&(end, ..), _) = intervals.last_key_value()?;
+        Some((start, end))
+    };
+    let overlap = match (span(&first_intervals), span(&second_intervals)) {
+        (Some((first_start, first_end)), Some((second_start, second_end))) => {
+            let range = DateRange {
+                start: first_start.max(second_start),
+                end: first_end.min(second_end),
+            };
+            (range.start <= range.end).then_some(range)
+        }
+        _ => None,
+    };
+
+    let mut diff = DatasetDiff {
+        first_total: first.total_energy(),
+        second_total: second.total_energy(),
+        overlap,
+        only_in_first: 0,
+        only_in_second: 0,
+        changed: 0,
+    };
+    for (interval, kilowatt_hours) in &first_intervals {
+        match second_intervals.get(interval) {
+            None => diff.only_in_first += 1,
+            // Exports round usage, so anything under a watt-hour apart counts as the same.
+            Some(other) if (other - kilowatt_hours).abs() >= 0.001 => diff.changed += 1,
+            Some(_) => {}
+        }
+    }
+    diff.only_in_second = second_intervals
+        .keys()
+        .filter(|interval| !first_intervals.contains_key(interval))
+        .count();
+    diff
+}
+
+fn diff_ui(
+    ui: &mut egui::Ui,
+    diff: &DatasetDiff,
+    date_format: DateFormat,
+    number_format: NumberFormat,
+) {
+    egui::Grid::new("dataset_diff")
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Total");
+            ui.label(format!(
+                "{} vs {}",
+                number_format.kilowatt_hours(diff.first_total),
+                number_format.kilowatt_hours(diff.second_total)
+            ));
+            ui.end_row();
+            ui.label("Overlap");
+            ui.label(match diff.overlap {
+                Some(range) => format!(
+                    "{} to {}",
+                    date_format.format(range.start),
+                    date_format.format(range.end)
+                ),
+                None => "None".to_owned(),
+            });
+            ui.end_row();
+            ui.label("Only in loaded file");
+            ui.label(diff.only_in_first.to_string());
+            ui.end_row();
+            ui.label("Only in this one");
+            ui.label(diff.only_in_second.to_string());
+            ui.end_row();
+            ui.label("Different usage");
+            ui.label(diff.changed.to_string());
+            ui.end_row();
+        });
+}
+
+/// Lists the comparisons for relabelling, recoloring, and removing, each with how it differs
+/// from `main`, the loaded file.
+pub fn comparisons_ui(
+    ui: &mut egui::Ui,
+    comparisons: &mut Vec<Dataset>,
+    main: Option<&UsageData>,
+    date_format: DateFormat,
+    number_format: NumberFormat,
+) {
+    if comparisons.is_empty() {
+        ui.label("Use File → Add comparison to overlay another file.");
+    }
+    let mut removed = None;
+    for (i, dataset) in comparisons.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba_unmultiplied(&mut dataset.color);
+                ui.add(egui::TextEdit::singleline(&mut dataset.label).desired_width(100.0));
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+            if let Some(main) = main {
+                ui.collapsing("Differences", |ui| {
+                    diff_ui(ui, &diff(main, &dataset.data), date_format, number_format);
+                });
+            }
+        });
+    }
+    if let Some(i) = removed {
+        comparisons.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn diff_counts_missing_and_changed_intervals() {
+        let original = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,
+Electric usage,1/15/2024,1:00 AM,2:00 AM,0.38,kWh,
+Electric usage,1/16/2024,12:00 AM,1:00 AM,1.0,kWh,
+",
+        );
+        let download = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,1:00 AM,2:00 AM,0.3805,kWh,
+Electric usage,1/16/2024,12:00 AM,1:00 AM,1.5,kWh,
+Electric usage,1/17/2024,12:00 AM,1:00 AM,2.0,kWh,
+",
+        );
+        let diff = diff(&original, &download);
+        assert!((diff.first_total - 1.8).abs() < 1e-9);
+        assert!((diff.second_total - 3.8805).abs() < 1e-9);
+        let overlap = diff.overlap.unwrap();
+        assert_eq!((overlap.start, overlap.end), (day(15), day(16)));
+        assert_eq!(diff.only_in_first, 1);
+        assert_eq!(diff.only_in_second, 1);
+        // Under a watt-hour apart is a rounding difference, not a change.
+        assert_eq!(diff.changed, 1);
+    }
+
+    #[test]
+    fn files_with_no_days_in_common_have_no_overlap() {
+        let first = UsageData::from_rows("Electric usage,1/15/2024,12:00 AM,1:00 AM,0.42,kWh,\n");
+        let second = UsageData::from_rows("Electric usage,1/17/2024,12:00 AM,1:00 AM,0.42,kWh,\n");
+        let apart = diff(&first, &second);
+        assert!(apart.overlap.is_none());
+        assert_eq!(
+            (apart.only_in_first, apart.only_in_second, apart.changed),
+            (1, 1, 0)
+        );
+        assert!(diff(&first, &UsageData::from_rows("")).overlap.is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! The selected day's cost as a rate, in currency per hour, which reads more naturally than cost
+//! per interval when intervals differ in length.
+
+use chrono::NaiveDate;
+
+use super::display::DisplaySettings;
+use super::UsageData;
+
+/// Draws a bar per entry on `date` at its cost divided by its length. `costs` is parallel to
+/// `data.entries` and may be empty until it's worked out.
+pub fn cost_rate_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    costs: &[f64],
+    settings: &DisplaySettings,
+    date: NaiveDate,
+) {
+    if costs.len() != data.entries.len() {
+        return;
+    }
+    let anchor = settings.bar_anchor;
+    let bars = data
+        .entries
+        .iter()
+        .zip(costs)
+        .filter(|(entry, _)| entry.day() == date && settings.tags.includes(entry))
+        .filter_map(|(entry, &cost)| {
+            let hours = entry.duration_hours();
+            // An interval with no length has no rate to show.
+            (hours > 0.0).then(|| {
+                egui_plot::Bar::new(entry.start_hours() + anchor.offset(hours), cost / hours)
+                    .width(hours)
+            })
+        })
+        .collect::<Vec<_>>();
+    if bars.is_empty() {
+        ui.label("No usage on this day.");
+        return;
+    }
+
+    let number_format = settings.number_format;
+    let time_format = settings.time_format;
+    egui_plot::Plot::new("cost_rate_plot")
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label(format!("{}/h", number_format.currency.symbol()))
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| time_format.format_hours(mark.value))
+        .y_axis_formatter(move |mark, _range| number_format.cost(mark.value))
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                egui_plot::BarChart::new(
+                    format!("Cost rate for {}", settings.date_format.format(date)),
+                    bars,
+                )
+                .color(settings.bar_color32())
+                .element_formatter(Box::new(move |bar, _chart| {
+                    format!(
+                        "{}\n{}/h",
+                        time_format.format_hours(bar.argument - anchor.offset(bar.bar_width)),
+                        number_format.cost(bar.value)
+                    )
+                })),
+            );
+        });
+}
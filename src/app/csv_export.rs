@@ -0,0 +1,265 @@
+//! Writing entries, daily totals, or a bill's tiers back out as CSV (or entries as JSON), for
+//! other tools to pick up.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use chrono::NaiveDate;
+
+use super::display::{DateFormat, NumberFormat, TimeFormat};
+use super::rates::TierUsage;
+use super::UsageEntry;
+
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct CsvFormat {
+    pub date_format: DateFormat,
+    pub decimals: usize,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            date_format: DateFormat::default(),
+            decimals: 3,
+        }
+    }
+}
+
+impl CsvFormat {
+    fn number_format(self) -> NumberFormat {
+        NumberFormat {
+            energy_decimals: self.decimals,
+            ..NumberFormat::default()
+        }
+    }
+}
+
+/// Quotes `field` if it would otherwise break the row apart.
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// One row per entry, with times in 24-hour form so they sort and parse predictably.
+pub fn entries_csv<'a>(
+    entries: impl IntoIterator<Item = &'a UsageEntry>,
+    format: CsvFormat,
+) -> String {
+    let number_format = format.number_format();
+    let time_format = TimeFormat::TwentyFourHour;
+    let mut csv = "date,start,end,kwh,note\n".to_owned();
+    for entry in entries {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            format.date_format.format(entry.day()),
+            time_format.format(entry.interval_start_hour, entry.interval_start_minute),
+            time_format.format(entry.interval_end_hour, entry.interval_end_minute),
+            number_format.energy(entry.kilowatt_hours),
+            escape(entry.note())
+        ));
+    }
+    csv
+}
+
+pub fn daily_totals_csv(daily_totals: &BTreeMap<NaiveDate, f64>, format: CsvFormat) -> String {
+    let number_format = format.number_format();
+    let mut csv = "date,kwh\n".to_owned();
+    for (&day, &total) in daily_totals {
+        csv.push_str(&format!(
+            "{},{}\n",
+            format.date_format.format(day),
+            number_format.energy(total)
+        ));
+    }
+    csv
+}
+
+/// One row per tier of a billing cycle's cost, then a total row. Rates and costs are written with
+/// `number_format`'s currency and precision, as the breakdown table shows them, so the export
+/// reads the same as the bill it's checked against.
+pub fn breakdown_csv(rows: &[TierUsage], format: CsvFormat, number_format: NumberFormat) -> String {
+    let energy_format = format.number_format();
+    let cost = |cost: f64| escape(&number_format.cost(cost));
+    let mut csv = "tier,kwh,rate,subtotal\n".to_owned();
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            escape(&row.name),
+            energy_format.energy(row.kilowatt_hours),
+            escape(&format!("{}/kWh", number_format.cost(row.price))),
+            cost(row.cost)
+        ));
+    }
+    csv.push_str(&format!(
+        "Total,{},,{}\n",
+        energy_format.energy(rows.iter().map(|row| row.kilowatt_hours).sum()),
+        cost(rows.iter().map(|row| row.cost).sum())
+    ));
+    csv
+}
+
+/// One row per bar as drawn on `date`, which may be an hour's entries rolled into one.
+pub fn bars_csv(date: NaiveDate, bars: &[(f64, f64, f64)], format: CsvFormat) -> String {
+    let number_format = format.number_format();
+    let time_format = TimeFormat::TwentyFourHour;
+    let mut csv = "date,start,end,kwh\n".to_owned();
+    for &(start, hours, kilowatt_hours) in bars {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            format.date_format.format(date),
+            time_format.format_hours(start),
+            time_format.format_hours(start + hours),
+            number_format.energy(kilowatt_hours)
+        ));
+    }
+    csv
+}
+
+/// Entries as a JSON array, in the same form they're persisted in.
+pub fn entries_json<'a>(
+    entries: impl IntoIterator<Item = &'a UsageEntry>,
+) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(
+        &entries.into_iter().collect::<Vec<_>>(),
+    )?)
+}
+
+/// Asks where to save, offering only `extension`, then writes `contents` there.
+fn save_as(
+    contents: &str,
+    file_name: &str,
+    filter_name: &str,
+    extension: &str,
+) -> anyhow::Result<()> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter(filter_name, &[extension])
+        .set_file_name(file_name)
+        .save_file()
+    else {
+        return Ok(());
+    };
+    std::fs::write(&path, contents).with_context(|| format!("Could not write {}", path.display()))
+}
+
+pub fn save_csv(csv: &str, file_name: &str) -> anyhow::Result<()> {
+    save_as(csv, file_name, "CSV", "csv")
+}
+
+pub fn save_json(json: &str, file_name: &str) -> anyhow::Result<()> {
+    save_as(json, file_name, "JSON", "json")
+}
+
+pub fn save_html(html: &str, file_name: &str) -> anyhow::Result<()> {
+    save_as(html, file_name, "HTML", "html")
+}
+
+pub fn csv_format_ui(ui: &mut egui::Ui, format: &mut CsvFormat) {
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut format.date_format, DateFormat::Iso, "YYYY-MM-DD");
+        ui.radio_value(
+            &mut format.date_format,
+            DateFormat::MonthDayYear,
+            "M/D/YYYY",
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Decimals");
+        ui.add(egui::DragValue::new(&mut format.decimals).range(0..=6));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::UsageData;
+
+    const ROWS: &str = "\
+Electric usage,1/5/2024,11:00 PM,12:00 AM,0.5125,kWh,Estimated \"late\"
+";
+
+    #[test]
+    fn entries_follow_the_chosen_format() {
+        let data = UsageData::from_rows(ROWS);
+        let iso = CsvFormat {
+            date_format: DateFormat::Iso,
+            decimals: 2,
+        };
+        assert_eq!(
+            entries_csv(&data.entries, iso),
+            "date,start,end,kwh,note\n2024-01-05,23:00,24:00,0.51,\"Estimated \"\"late\"\"\"\n"
+        );
+        let original = CsvFormat {
+            date_format: DateFormat::MonthDayYear,
+            decimals: 4,
+        };
+        let csv = entries_csv(&data.entries, original);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("1/5/2024,23:00,24:00,0.5125,\"Estimated \"\"late\"\"\"")
+        );
+    }
+
+    #[test]
+    fn daily_totals_follow_the_chosen_format() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let totals = BTreeMap::from([(day, 12.0)]);
+        let format = CsvFormat {
+            date_format: DateFormat::DayMonthYear,
+            decimals: 1,
+        };
+        assert_eq!(
+            daily_totals_csv(&totals, format),
+            "date,kwh\n5/1/2024,12.0\n"
+        );
+    }
+
+    #[test]
+    fn breakdown_rows_are_priced_as_the_table_shows_them() {
+        use crate::app::display::{Currency, Grouping};
+        use crate::app::rates::{RateSchedule, RateTier};
+
+        let schedule = RateSchedule {
+            cycle_start_day: 1,
+            tiers: vec![
+                RateTier {
+                    name: "Baseline".to_owned(),
+                    allowance: Some(1000.0),
+                    price: 0.1,
+                },
+                RateTier {
+                    name: "Peak, summer".to_owned(),
+                    allowance: None,
+                    price: 0.25,
+                },
+            ],
+        };
+        let rows = schedule.breakdown(11000.0);
+        let format = CsvFormat {
+            date_format: DateFormat::Iso,
+            decimals: 1,
+        };
+        let number_format = NumberFormat {
+            currency: Currency::Euro,
+            grouping: Grouping::Comma,
+            ..NumberFormat::default()
+        };
+        assert_eq!(
+            breakdown_csv(&rows, format, number_format),
+            "tier,kwh,rate,subtotal\n\
+             Baseline,1000.0,€0.10/kWh,€100.00\n\
+             \"Peak, summer\",10000.0,€0.25/kWh,\"€2,500.00\"\n\
+             Total,11000.0,,\"€2,600.00\"\n"
+        );
+    }
+
+    #[test]
+    fn fields_that_would_split_the_row_are_quoted() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a, b"), "\"a, b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
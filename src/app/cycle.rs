@@ -0,0 +1,109 @@
+//! Usage adding up over a billing cycle, against the allowances where each rate tier runs out,
+//! so it's clear when the cycle crosses into a more expensive tier.
+
+use chrono::{Days, Months, NaiveDate};
+
+use super::display::DisplaySettings;
+use super::rates::RateSchedule;
+use super::UsageData;
+
+/// Where each tier with an allowance runs out, in kWh into the cycle, with the tier's name. The
+/// last tier never runs out, since usage past it is still billed there.
+fn thresholds(schedule: &RateSchedule) -> Vec<(f64, &str)> {
+    let mut used = 0.0;
+    let tiers = schedule.tiers.len().saturating_sub(1);
+    schedule.tiers[..tiers]
+        .iter()
+        .map_while(|tier| {
+            used += tier.allowance?;
+            Some((used, tier.name.as_str()))
+        })
+        .collect()
+}
+
+/// A running total of the included entries in the billing cycle containing `date`, with a line
+/// at each tier threshold. The x axis counts days into the cycle.
+pub fn cycle_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    schedule: &RateSchedule,
+    settings: &DisplaySettings,
+    date: NaiveDate,
+) {
+    let start = schedule.cycle_start(date);
+    let end = start + Months::new(1);
+    let mut entries = data
+        .included()
+        .filter(|entry| (start..end).contains(&entry.day()))
+        .collect::<Vec<_>>();
+    if entries.is_empty() {
+        ui.label("No usage in this billing cycle.");
+        return;
+    }
+    entries.sort_by_key(|entry| entry.start());
+
+    let origin = start.and_hms_opt(0, 0, 0).unwrap();
+    let mut used = 0.0;
+    let mut points = vec![[0.0, 0.0]];
+    for entry in entries {
+        used += entry.kilowatt_hours;
+        let end_of_entry =
+            (entry.start() - origin).num_minutes() as f64 / 60.0 + entry.duration_hours();
+        points.push([end_of_entry / 24.0, used]);
+    }
+    let cycle_days = (end - start).num_days() as f64;
+    let thresholds = thresholds(schedule);
+    // Thresholds far above the usage would flatten the line, so only the next one up is
+    // brought into view.
+    let next_threshold = thresholds
+        .iter()
+        .map(|&(threshold, _)| threshold)
+        .find(|&threshold| threshold > used);
+
+    let number_format = settings.number_format;
+    let date_format = settings.date_format;
+    let label = move |x: f64| {
+        u64::try_from(x.floor() as i64)
+            .ok()
+            .and_then(|offset| start.checked_add_days(Days::new(offset)))
+            .map(|day| date_format.format(day))
+            .unwrap_or_default()
+    };
+    let y_label = move |y: f64| format!("{} kWh", number_format.energy(y));
+    egui_plot::Plot::new("cycle_plot")
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label("Used this cycle (kWh)")
+        .include_x(0.0)
+        .include_x(cycle_days)
+        .include_y(0.0)
+        .include_y(next_threshold.unwrap_or(used))
+        .x_axis_formatter(move |mark, _range| label(mark.value))
+        .label_formatter(move |_name, point| format!("{}\n{}", label(point.x), y_label(point.y)))
+        .show(ui, |plot_ui| {
+            for &(threshold, name) in &thresholds {
+                if threshold > next_threshold.unwrap_or(f64::MAX) {
+                    break;
+                }
+                plot_ui.hline(
+                    egui_plot::HLine::new("", threshold)
+                        .color(plot_ui.ctx().style().visuals.warn_fg_color),
+                );
+                plot_ui.text(
+                    egui_plot::Text::new(
+                        "",
+                        egui_plot::PlotPoint::new(0.0, threshold),
+                        format!("End of {name}: {}", y_label(threshold)),
+                    )
+                    .anchor(egui::Align2::LEFT_BOTTOM),
+                );
+            }
+            plot_ui.line(
+                egui_plot::Line::new(
+                    format!("Cycle from {}", date_format.format(start)),
+                    egui_plot::PlotPoints::from(points),
+                )
+                .color(settings.bar_color32()),
+            );
+        });
+}
@@ -0,0 +1,880 @@
+//! Hourly bars for a single day, with the day's entries tabled underneath.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::comparison::Dataset;
+use super::display::{
+    self, BarAnchor, DateFormat, DisplaySettings, NumberFormat, TimeFormat, YScale,
+};
+use super::{svg, table, DayFilter, UsageData, UsageEntry};
+
+/// Identifies the selected interval by its day and start time, so it survives reloads and simply
+/// matches nothing once that interval is gone.
+pub type Selection = (NaiveDate, u8, u8);
+
+fn selection_id() -> egui::Id {
+    egui::Id::new("selected_interval")
+}
+
+fn window_id() -> egui::Id {
+    egui::Id::new("selected_window")
+}
+
+/// A stretch of `date` picked by shift-dragging across the plot, in hours since midnight. `to`
+/// is wherever the drag ended, so it may come before `from`.
+#[derive(Clone, Copy)]
+struct Window {
+    date: NaiveDate,
+    from: f64,
+    to: f64,
+}
+
+impl Window {
+    fn hours(self) -> (f64, f64) {
+        (self.from.min(self.to), self.from.max(self.to))
+    }
+}
+
+fn exclusion_toggle_id() -> egui::Id {
+    egui::Id::new("exclusion_toggle")
+}
+
+/// The interval whose excluded box was clicked in the table last frame, to toggle. The table
+/// only borrows the data, so the toggle is passed back to the app this way.
+pub fn take_exclusion_toggle(ctx: &egui::Context) -> Option<Selection> {
+    ctx.data_mut(|data| data.remove_temp::<Selection>(exclusion_toggle_id()))
+}
+
+fn visible_export_id() -> egui::Id {
+    egui::Id::new("visible_export")
+}
+
+/// The bars in view when the plot's context menu asked for them to be exported.
+#[derive(Clone, Default)]
+pub struct VisibleBars {
+    pub date: NaiveDate,
+    /// Each bar's start and length in hours since midnight, and its energy in kWh.
+    pub bars: Vec<(f64, f64, f64)>,
+}
+
+/// The bars the plot's context menu asked to export last frame. Like the exclusion toggle, the
+/// export is left to the app, which knows how it wants CSV written.
+pub fn take_visible_export(ctx: &egui::Context) -> Option<VisibleBars> {
+    ctx.data_mut(|data| data.remove_temp::<VisibleBars>(visible_export_id()))
+}
+
+fn selection_of(entry: &UsageEntry) -> Selection {
+    (
+        entry.day(),
+        entry.interval_start_hour,
+        entry.interval_start_minute,
+    )
+}
+
+/// Where a bar goes and how much it stands for, before any display transformation.
+struct BarPoint {
+    /// Where the bar is centered, per the [`BarAnchor`].
+    x: f64,
+    /// When the interval the bar covers starts, in hours since midnight.
+    start: f64,
+    width: f64,
+    kilowatt_hours: f64,
+    estimated: bool,
+    excluded: bool,
+}
+
+/// One point per entry, as wide as its interval. With `roll_up`, sub-hourly entries are summed
+/// into one point per hour, which counts as estimated if any of its entries were. Excluded
+/// entries have nothing to add to an hour, so they're left out of rolled-up points.
+fn bar_points(entries: &[&UsageEntry], roll_up: bool, anchor: BarAnchor) -> Vec<BarPoint> {
+    if roll_up {
+        let mut hours = BTreeMap::new();
+        for entry in entries.iter().filter(|entry| !entry.excluded) {
+            let hour = hours.entry(entry.hour_bucket()).or_insert((0.0, false));
+            hour.0 += entry.kilowatt_hours;
+            hour.1 |= entry.is_estimated();
+        }
+        hours
+            .into_iter()
+            .map(|(hour, (kilowatt_hours, estimated))| BarPoint {
+                x: hour as f64 + anchor.offset(1.0),
+                start: hour as f64,
+                width: 1.0,
+                kilowatt_hours,
+                estimated,
+                excluded: false,
+            })
+            .collect()
+    } else {
+        entries
+            .iter()
+            .map(|entry| BarPoint {
+                x: entry.start_hours() + anchor.offset(entry.duration_hours()),
+                start: entry.start_hours(),
+                width: entry.duration_hours(),
+                kilowatt_hours: entry.kilowatt_hours,
+                estimated: entry.is_estimated(),
+                excluded: entry.excluded,
+            })
+            .collect()
+    }
+}
+
+/// The total of the included bars in `points`, which normalized values are a percentage of.
+fn included_total(points: &[BarPoint]) -> f64 {
+    points
+        .iter()
+        .filter(|point| !point.excluded)
+        .map(|point| point.kilowatt_hours)
+        .sum()
+}
+
+/// One bar per point, in the same order. With `normalize`, bars are drawn as a percentage of the
+/// points' total.
+fn day_bars(
+    points: &[BarPoint],
+    to_display: impl Fn(f64, f64) -> f64,
+    y_scale: YScale,
+    normalize: bool,
+) -> Vec<egui_plot::Bar> {
+    let day_total = included_total(points);
+    // A day with nothing used has no shape to show as percentages.
+    if normalize && day_total == 0.0 {
+        return Vec::new();
+    }
+    points
+        .iter()
+        .map(|point| {
+            let value = to_display(point.kilowatt_hours, day_total);
+            egui_plot::Bar::new(point.x, y_scale.to_plot(value) - y_scale.base())
+                .base_offset(y_scale.base())
+                .width(point.width)
+        })
+        .collect()
+}
+
+const COST_SERIES: &str = "Cost";
+
+/// The cost of each hour of `entries`, given each entry's own cost in `entry_costs`.
+fn hourly_costs(entries: &[&UsageEntry], entry_costs: &[f64]) -> BTreeMap<u8, f64> {
+    let mut costs = BTreeMap::new();
+    for (entry, cost) in entries.iter().zip(entry_costs) {
+        *costs.entry(entry.hour_bucket()).or_insert(0.0) += cost;
+    }
+    costs
+}
+
+/// A color from cool at 0 to warm at 1, for coloring bars by how large they are.
+fn gradient_color(t: f32) -> egui::Color32 {
+    const COOL: egui::Color32 = egui::Color32::from_rgb(60, 120, 220);
+    const WARM: egui::Color32 = egui::Color32::from_rgb(220, 60, 40);
+    COOL.lerp_to_gamma(WARM, t.clamp(0.0, 1.0))
+}
+
+/// Outlines the bar whose point covers `selected_hours`, if any.
+fn highlight_selected(
+    bar: egui_plot::Bar,
+    point: &BarPoint,
+    selected_hours: Option<f64>,
+    visuals: &egui::Visuals,
+) -> egui_plot::Bar {
+    match selected_hours {
+        Some(hours) if (point.start..point.start + point.width).contains(&hours) => bar
+            .fill(visuals.selection.bg_fill)
+            .stroke(egui::Stroke::new(2.0, visuals.strong_text_color())),
+        _ => bar,
+    }
+}
+
+/// The selected interval's exact figures. Returns whether it was closed.
+fn interval_details_ui(
+    ui: &mut egui::Ui,
+    entry: &UsageEntry,
+    cost: Option<f64>,
+    settings: &DisplaySettings,
+) -> bool {
+    let time_format = settings.time_format;
+    let number_format = settings.number_format;
+
+    let mut closed = false;
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.strong(format!(
+                "{} {}\u{2013}{}",
+                settings.date_format.format(entry.day()),
+                time_format.format(entry.interval_start_hour, entry.interval_start_minute),
+                time_format.format(entry.interval_end_hour, entry.interval_end_minute),
+            ));
+            closed = ui.small_button("Close").clicked();
+        });
+        ui.label(format!(
+            "{} ({} kW average)",
+            number_format.kilowatt_hours(entry.kilowatt_hours),
+            number_format.energy(entry.kilowatt_hours / entry.duration_hours()),
+        ));
+        if let Some(cost) = cost {
+            display::cost_label(
+                ui,
+                cost,
+                format!("Estimated cost: {}", number_format.cost(cost)),
+            );
+        }
+        if let Some(note) = &entry.note {
+            ui.label(format!("Note: {note}"));
+        }
+    });
+    closed
+}
+
+type BarFormatter = Box<dyn Fn(&egui_plot::Bar, &egui_plot::BarChart) -> String>;
+
+/// Hover text for a bar, undoing `y_scale` so the real value is shown.
+fn bar_formatter(
+    name: String,
+    y_scale: YScale,
+    number_format: NumberFormat,
+    time_format: TimeFormat,
+    unit_label: &'static str,
+) -> BarFormatter {
+    Box::new(move |bar, _chart| {
+        format!(
+            "{name}\n{}\n{} {unit_label}",
+            time_format.format_hours(bar.argument),
+            number_format.energy(y_scale.to_value(bar.base_offset.unwrap_or(0.0) + bar.value)),
+        )
+    })
+}
+
+/// Shades `window` on the plot, from `bottom` to `top`, with the energy and cost of the entries
+/// starting inside it written across its top.
+fn window_ui(
+    plot_ui: &mut egui_plot::PlotUi<'_>,
+    window: Window,
+    entries: &[&UsageEntry],
+    entry_costs: &[Option<f64>],
+    top: f64,
+    bottom: f64,
+    settings: &DisplaySettings,
+) {
+    let (from, to) = window.hours();
+    if to - from <= 0.0 {
+        return;
+    }
+    let (energy, cost) = entries
+        .iter()
+        .zip(entry_costs)
+        .filter(|(entry, _)| !entry.excluded && (from..to).contains(&entry.start_hours()))
+        .fold((0.0, Some(0.0)), |(energy, total_cost), (entry, cost)| {
+            (
+                energy + entry.kilowatt_hours,
+                total_cost.zip(*cost).map(|(total, cost)| total + cost),
+            )
+        });
+
+    let color = plot_ui.ctx().style().visuals.selection.bg_fill;
+    plot_ui.polygon(
+        egui_plot::Polygon::new(
+            "",
+            vec![[from, bottom], [to, bottom], [to, top], [from, top]],
+        )
+        .fill_color(color.gamma_multiply(0.25))
+        .stroke(egui::Stroke::new(1.0, color)),
+    );
+    let time_format = settings.time_format;
+    let number_format = settings.number_format;
+    let mut readout = format!(
+        "{}–{}\n{} {}",
+        time_format.format_hours(from.max(0.0)),
+        time_format.format_hours(to.min(24.0)),
+        number_format.energy(settings.unit.convert(energy)),
+        settings.unit.label()
+    );
+    if let Some(cost) = cost {
+        readout.push_str(&format!(", {}", number_format.cost(cost)));
+    }
+    plot_ui.text(
+        egui_plot::Text::new(
+            "",
+            egui_plot::PlotPoint::new((from + to) / 2.0, top),
+            egui::RichText::new(readout).strong(),
+        )
+        .anchor(egui::Align2::CENTER_TOP),
+    );
+}
+
+/// The included bar drawn across `hour`, judged the same way as a click selects a bar.
+fn point_at(points: &[BarPoint], hour: f64) -> Option<&BarPoint> {
+    points
+        .iter()
+        .filter(|point| !point.excluded)
+        .find(|point| (hour - point.x).abs() <= point.width / 2.0)
+}
+
+/// A vertical line at `hour` with every series' value there listed beside it, so comparison
+/// files can be read off against the day hour by hour. Each series' value is whichever of its
+/// bars is drawn across `hour`, shown via `to_display` against the series' own total. `series`
+/// should have every bar, including any too small to draw.
+fn crosshair_ui(
+    plot_ui: &mut egui_plot::PlotUi<'_>,
+    hour: f64,
+    series: &[(&str, &[BarPoint])],
+    to_display: impl Fn(f64, f64) -> f64,
+    unit_label: &str,
+    settings: &DisplaySettings,
+) {
+    let color = plot_ui.ctx().style().visuals.weak_text_color();
+    plot_ui.vline(egui_plot::VLine::new("", hour).color(color));
+    let number_format = settings.number_format;
+    let mut readout = settings.time_format.format_hours(hour.clamp(0.0, 24.0));
+    for (name, points) in series {
+        let total = included_total(points);
+        let value = point_at(points, hour).map_or("–".to_owned(), |point| {
+            format!(
+                "{} {unit_label}",
+                number_format.energy(to_display(point.kilowatt_hours, total))
+            )
+        });
+        readout.push_str(&format!("\n{name}: {value}"));
+    }
+    let top = plot_ui.plot_bounds().max()[1];
+    plot_ui.text(
+        egui_plot::Text::new("", egui_plot::PlotPoint::new(hour, top), readout)
+            .anchor(egui::Align2::LEFT_TOP),
+    );
+}
+
+fn chart_title(date_format: DateFormat, date: NaiveDate) -> String {
+    format!("Usage for {}", date_format.format(date))
+}
+
+pub fn day_view_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    comparisons: &[Dataset],
+    filter: &DayFilter,
+    costs: &[f64],
+    settings: &mut DisplaySettings,
+    date: NaiveDate,
+) -> anyhow::Result<()> {
+    let mut reset_view = false;
+    let mut export_svg = false;
+    let report_mode = settings.report_mode;
+    if report_mode {
+        ui.heading(chart_title(settings.date_format, date));
+    } else {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut settings.show_typical_day, "Show typical day");
+            ui.checkbox(&mut settings.show_cost, "Show cost");
+            ui.checkbox(&mut settings.color_by_value, "Color by value");
+            ui.checkbox(&mut settings.lock_view, "Lock view");
+            reset_view = ui.button("Reset view").clicked();
+            export_svg = ui.button("Export SVG…").clicked();
+            ui.weak("Shift-drag across the chart to total a stretch of it");
+        });
+    }
+
+    // `costs` is parallel to `data.entries`, but is empty for the frame after new data is loaded
+    // and before its costs are worked out again.
+    let (entries, entry_costs): (Vec<_>, Vec<_>) = data
+        .entries
+        .iter()
+        .zip(
+            costs
+                .iter()
+                .copied()
+                .map(Some)
+                .chain(std::iter::repeat(None)),
+        )
+        .filter(|(entry, _)| entry.day() == date && settings.tags.includes(entry))
+        .unzip();
+
+    let mut selection = ui.data(|data| data.get_temp::<Selection>(selection_id()));
+    let selected = entries
+        .iter()
+        .position(|entry| Some(selection_of(entry)) == selection);
+    if let Some(i) = selected.filter(|_| !report_mode) {
+        if interval_details_ui(ui, entries[i], entry_costs[i], settings) {
+            selection = None;
+        }
+    }
+    let selected_hours = selected.map(|i| entries[i].start_hours());
+
+    // With `normalize`, values are a percentage of their day's total rather than energy.
+    let normalize = settings.normalize;
+    let y_scale = settings.y_scale;
+    let unit_label = if normalize {
+        "%"
+    } else {
+        settings.unit.label()
+    };
+    let unit = settings.unit;
+    let number_format = settings.number_format;
+    let time_format = settings.time_format;
+    let to_display = move |kilowatt_hours: f64, total: f64| {
+        if normalize {
+            kilowatt_hours / total * 100.0
+        } else {
+            unit.convert(kilowatt_hours)
+        }
+    };
+
+    let anchor = settings.bar_anchor;
+    let all_points = bar_points(&entries, settings.roll_up_hourly, anchor);
+    let bars = day_bars(&all_points, to_display, y_scale, normalize);
+
+    // Cost has no axis of its own in egui_plot, so it's scaled to peak with the bars and given a
+    // second axis on the right that undoes the scaling.
+    let costs = settings
+        .show_cost
+        .then(|| entry_costs.iter().copied().collect::<Option<Vec<_>>>())
+        .flatten()
+        .map(|entry_costs| hourly_costs(&entries, &entry_costs));
+    let day_total = included_total(&all_points);
+    let bar_peak = all_points
+        .iter()
+        .map(|point| to_display(point.kilowatt_hours, day_total))
+        .fold(0.0, f64::max);
+
+    // Tiny bars are only hidden from the chart; totals, the bars' scale, and the crosshair still
+    // count them.
+    let min_bar = settings.min_bar_kilowatt_hours;
+    let hidden = all_points
+        .iter()
+        .filter(|point| point.kilowatt_hours.abs() < min_bar)
+        .count();
+    let (bars, points): (Vec<_>, Vec<_>) = bars
+        .into_iter()
+        .zip(&all_points)
+        .filter(|(_, point)| point.kilowatt_hours.abs() >= min_bar)
+        .unzip();
+    if hidden > 0 && !report_mode {
+        ui.weak(format!(
+            "{hidden} bars under {} hidden",
+            number_format.kilowatt_hours(min_bar)
+        ));
+    }
+    let cost_peak = costs
+        .iter()
+        .flat_map(|costs| costs.values().copied())
+        .fold(0.0, f64::max);
+    let cost_scale = if cost_peak > 0.0 {
+        bar_peak / cost_peak
+    } else {
+        1.0
+    };
+
+    let y_label = if normalize {
+        "Share of day (%)".to_owned()
+    } else {
+        format!("Usage ({unit_label})")
+    };
+    let mut plot = egui_plot::Plot::new("Power Usage Chart")
+        .legend(display::legend())
+        .show_axes(true)
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless);
+    if costs.is_some() {
+        plot = plot.custom_y_axes(vec![
+            egui_plot::AxisHints::new_y(),
+            egui_plot::AxisHints::new_y()
+                .label("Cost")
+                .placement(egui_plot::HPlacement::Right)
+                .formatter(move |mark, _range| {
+                    number_format.cost(y_scale.to_value(mark.value) / cost_scale)
+                }),
+        ]);
+    }
+    plot = plot
+        .y_axis_label(y_label.clone())
+        .x_axis_formatter(move |mark, _range| time_format.format_hours(mark.value))
+        .label_formatter(move |name, point| {
+            let value = if name == COST_SERIES {
+                number_format.cost(y_scale.to_value(point.y) / cost_scale)
+            } else {
+                format!(
+                    "{} {unit_label}",
+                    number_format.energy(y_scale.to_value(point.y))
+                )
+            };
+            format!("{name}\n{}\n{value}", time_format.format_hours(point.x))
+        });
+    if normalize {
+        plot = plot
+            .include_y(y_scale.to_plot(0.0))
+            .include_y(y_scale.to_plot(100.0));
+    }
+    if y_scale == YScale::Log {
+        plot = plot.y_axis_formatter(move |mark, _range| {
+            number_format.energy(y_scale.to_value(mark.value))
+        });
+    }
+    if settings.lock_view {
+        plot = plot
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false)
+            .allow_double_click_reset(false)
+            .auto_bounds(egui::Vec2b::TRUE);
+    }
+    // Shift turns dragging from panning into picking a window.
+    let picking_window = ui.input(|input| input.modifiers.shift);
+    if picking_window {
+        plot = plot.allow_drag(false).allow_boxed_zoom(false);
+    }
+    if reset_view {
+        plot = plot.reset();
+    }
+    let show_table = settings.plot_height_fraction < 1.0 && !report_mode;
+    if show_table {
+        plot = plot.height(ui.available_height() * settings.plot_height_fraction);
+    }
+
+    let title = chart_title(settings.date_format, date);
+    let bar_color = settings.bar_color32();
+    // The day's largest bar, which the gradient tops out at.
+    let gradient_peak = settings
+        .color_by_value
+        .then(|| {
+            points
+                .iter()
+                .map(|point| point.kilowatt_hours)
+                .fold(0.0, f64::max)
+        })
+        .filter(|&peak| peak > 0.0);
+    let mut export_result = Ok(());
+    if export_svg {
+        let svg = svg::bar_chart_svg(
+            &title,
+            &y_label,
+            &bars,
+            bar_color,
+            |hours| time_format.format_hours(hours),
+            |y| number_format.energy(y_scale.to_value(y)),
+        );
+        export_result = svg::export_svg(&svg, &format!("usage-{date}.svg"));
+    }
+
+    let visuals = ui.visuals().clone();
+    let cost_color = visuals.warn_fg_color;
+    let mut window = ui
+        .data(|data| data.get_temp::<Window>(window_id()))
+        .filter(|window| window.date == date);
+    let mut visible_hours = (f64::NEG_INFINITY, f64::INFINITY);
+    let response = plot
+        .show(ui, |plot_ui| {
+            let bounds = plot_ui.plot_bounds();
+            visible_hours = (bounds.min()[0], bounds.max()[0]);
+            if picking_window {
+                let response = plot_ui.response();
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    if response.drag_started() {
+                        window = Some(Window {
+                            date,
+                            from: pointer.x,
+                            to: pointer.x,
+                        });
+                    } else if let Some(window) = window.as_mut().filter(|_| response.dragged()) {
+                        window.to = pointer.x;
+                    }
+                }
+            } else if plot_ui.response().clicked() {
+                window = None;
+            }
+            if plot_ui.response().clicked() {
+                // Clicking between bars clears the selection.
+                selection = plot_ui.pointer_coordinate().and_then(|pointer| {
+                    entries
+                        .iter()
+                        .find(|entry| {
+                            let width = entry.duration_hours();
+                            let x = entry.start_hours() + anchor.offset(width);
+                            (pointer.x - x).abs() <= width / 2.0
+                        })
+                        .map(|entry| selection_of(entry))
+                });
+            }
+
+            let (estimated, metered): (Vec<_>, Vec<_>) = bars
+                .into_iter()
+                .zip(&points)
+                .map(|(bar, point)| {
+                    let bar = match gradient_peak {
+                        Some(peak) if !point.estimated => {
+                            let color = gradient_color((point.kilowatt_hours / peak) as f32);
+                            bar.fill(color.linear_multiply(0.2))
+                                .stroke(egui::Stroke::new(1.0, color))
+                        }
+                        _ => bar,
+                    };
+                    (
+                        highlight_selected(bar, point, selected_hours, &visuals),
+                        point,
+                    )
+                })
+                .partition(|(_, point)| point.estimated || point.excluded);
+            let (excluded, estimated): (Vec<_>, Vec<_>) =
+                estimated.into_iter().partition(|(_, point)| point.excluded);
+            plot_ui.bar_chart(
+                egui_plot::BarChart::new(
+                    "Usage",
+                    metered.into_iter().map(|(bar, _)| bar).collect(),
+                )
+                .color(bar_color)
+                .element_formatter(bar_formatter(
+                    title,
+                    y_scale,
+                    number_format,
+                    time_format,
+                    unit_label,
+                )),
+            );
+            // Estimated readings are less trustworthy, so they're drawn faded in their own series.
+            if !estimated.is_empty() {
+                plot_ui.bar_chart(
+                    egui_plot::BarChart::new(
+                        "Estimated",
+                        estimated.into_iter().map(|(bar, _)| bar).collect(),
+                    )
+                    .color(egui::Color32::GRAY.gamma_multiply(0.5))
+                    .element_formatter(bar_formatter(
+                        "Estimated".to_owned(),
+                        y_scale,
+                        number_format,
+                        time_format,
+                        unit_label,
+                    )),
+                );
+            }
+            // Excluded readings are kept only as a ghost of where they were.
+            if !excluded.is_empty() {
+                plot_ui.bar_chart(
+                    egui_plot::BarChart::new(
+                        "Excluded",
+                        excluded.into_iter().map(|(bar, _)| bar).collect(),
+                    )
+                    .color(egui::Color32::GRAY.gamma_multiply(0.15))
+                    .element_formatter(bar_formatter(
+                        "Excluded".to_owned(),
+                        y_scale,
+                        number_format,
+                        time_format,
+                        unit_label,
+                    )),
+                );
+            }
+            let mut comparison_points = Vec::new();
+            for dataset in comparisons {
+                let entries = dataset
+                    .data
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.day() == date && settings.tags.includes(entry))
+                    .collect::<Vec<_>>();
+                let dataset_points = bar_points(&entries, settings.roll_up_hourly, anchor);
+                let bars = day_bars(&dataset_points, to_display, y_scale, normalize)
+                    .into_iter()
+                    .map(|bar| {
+                        let width = bar.bar_width * 0.6;
+                        bar.width(width)
+                    })
+                    .collect();
+                plot_ui.bar_chart(
+                    egui_plot::BarChart::new(dataset.label.clone(), bars)
+                        .color(dataset.color32())
+                        .element_formatter(bar_formatter(
+                            dataset.label.clone(),
+                            y_scale,
+                            number_format,
+                            time_format,
+                            unit_label,
+                        )),
+                );
+                comparison_points.push((dataset.label.as_str(), dataset_points));
+            }
+            // The crosshair is only worth its clutter with something to compare against.
+            if let Some(pointer) = plot_ui
+                .pointer_coordinate()
+                .filter(|_| !comparison_points.is_empty() && !picking_window)
+            {
+                let series = std::iter::once(("Usage", all_points.as_slice()))
+                    .chain(
+                        comparison_points
+                            .iter()
+                            .map(|(label, points)| (*label, points.as_slice())),
+                    )
+                    .collect::<Vec<_>>();
+                crosshair_ui(
+                    plot_ui, pointer.x, &series, to_display, unit_label, settings,
+                );
+            }
+            if let Some(costs) = &costs {
+                plot_ui.line(
+                    egui_plot::Line::new(
+                        COST_SERIES,
+                        costs
+                            .iter()
+                            .map(|(&hour, &cost)| {
+                                [
+                                    hour as f64 + anchor.offset(1.0),
+                                    y_scale.to_plot(cost * cost_scale),
+                                ]
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .color(cost_color),
+                );
+            }
+            if settings.show_typical_day {
+                let typical_day = data.typical_day(filter);
+                let typical_total: f64 = typical_day.iter().sum();
+                if !(normalize && typical_total == 0.0) {
+                    plot_ui.line(egui_plot::Line::new(
+                        "Typical day",
+                        (0..24)
+                            .map(|hour| {
+                                let value = to_display(typical_day[hour], typical_total);
+                                [hour as f64 + anchor.offset(1.0), y_scale.to_plot(value)]
+                            })
+                            .collect::<Vec<_>>(),
+                    ));
+                }
+            }
+            if let Some(window) = window {
+                window_ui(
+                    plot_ui,
+                    window,
+                    &entries,
+                    &entry_costs,
+                    y_scale.to_plot(bar_peak),
+                    y_scale.base(),
+                    settings,
+                );
+            }
+        })
+        .response;
+    response.context_menu(|ui| {
+        if ui.button("Export visible data as CSV").clicked() {
+            // Only what's drawn: bars hidden by the threshold are already gone from `points`,
+            // and excluded ones are just ghosts. A bar counts if any of it is in view.
+            let (from, to) = visible_hours;
+            let bars = points
+                .iter()
+                .filter(|point| !point.excluded)
+                .filter(|point| (point.x - to).max(from - point.x) < point.width / 2.0)
+                .map(|point| (point.start, point.width, point.kilowatt_hours))
+                .collect();
+            ui.data_mut(|data| {
+                data.insert_temp(visible_export_id(), VisibleBars { date, bars });
+            });
+            ui.close_menu();
+        }
+    });
+
+    ui.data_mut(|data| match selection {
+        Some(selection) => data.insert_temp(selection_id(), selection),
+        None => data.remove::<Selection>(selection_id()),
+    });
+    ui.data_mut(|data| match window {
+        Some(window) => data.insert_temp(window_id(), window),
+        None => data.remove::<Window>(window_id()),
+    });
+
+    if show_table {
+        if let Some(i) = table::entries_table_ui(
+            ui,
+            &entries,
+            number_format,
+            time_format,
+            settings.note_length,
+        ) {
+            ui.data_mut(|data| data.insert_temp(exclusion_toggle_id(), selection_of(entries[i])));
+        }
+    }
+    export_result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two hours of quarter-hour readings.
+    const QUARTER_HOURS: &str = "\
+Electric usage,1/15/2024,1:00 AM,1:15 AM,0.10,kWh,
+Electric usage,1/15/2024,1:15 AM,1:30 AM,0.20,kWh,
+Electric usage,1/15/2024,1:30 AM,1:45 AM,0.30,kWh,Estimated
+Electric usage,1/15/2024,1:45 AM,2:00 AM,0.40,kWh,
+Electric usage,1/15/2024,2:00 AM,2:15 AM,0.50,kWh,
+Electric usage,1/15/2024,2:15 AM,2:30 AM,0.60,kWh,
+Electric usage,1/15/2024,2:30 AM,2:45 AM,0.70,kWh,
+Electric usage,1/15/2024,2:45 AM,3:00 AM,0.80,kWh,
+";
+
+    fn total(points: &[BarPoint]) -> f64 {
+        points.iter().map(|point| point.kilowatt_hours).sum()
+    }
+
+    #[test]
+    fn rolling_up_keeps_the_total() {
+        let data = UsageData::from_rows(QUARTER_HOURS);
+        let entries = data.entries.iter().collect::<Vec<_>>();
+        let fine = bar_points(&entries, false, BarAnchor::Start);
+        let hourly = bar_points(&entries, true, BarAnchor::Start);
+        assert_eq!(fine.len(), 8);
+        assert_eq!(hourly.len(), 2);
+        assert!((total(&fine) - total(&hourly)).abs() < 1e-9);
+        assert!((hourly[0].kilowatt_hours - 1.0).abs() < 1e-9);
+        assert_eq!((hourly[0].start, hourly[0].width), (1.0, 1.0));
+        assert!(hourly[0].estimated);
+        assert!(!hourly[1].estimated);
+    }
+
+    /// Hourly points from 1:00 to 4:00, each bar centered per `anchor`, with the second
+    /// excluded.
+    fn hourly(anchor: BarAnchor) -> Vec<BarPoint> {
+        let mut data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,1:00 AM,2:00 AM,1.0,kWh,
+Electric usage,1/15/2024,2:00 AM,3:00 AM,9.0,kWh,
+Electric usage,1/15/2024,3:00 AM,4:00 AM,0.001,kWh,
+",
+        );
+        data.entries[1].excluded = true;
+        bar_points(&data.entries.iter().collect::<Vec<_>>(), false, anchor)
+    }
+
+    #[test]
+    fn the_crosshair_reads_the_bar_drawn_under_it() {
+        let points = hourly(BarAnchor::Start);
+        let at = |hour| point_at(&points, hour).map(|point| point.kilowatt_hours);
+        assert_eq!(at(1.2), Some(1.0));
+        // Excluded bars have no reading, and tiny ones are still read.
+        assert_eq!(at(2.2), None);
+        assert_eq!(at(3.4), Some(0.001));
+        assert_eq!(at(3.7), None);
+        assert!((included_total(&points) - 1.001).abs() < 1e-9);
+
+        // Bars centered on the end of their hour are read where they're drawn, not by the
+        // hour the interval covers.
+        let points = hourly(BarAnchor::End);
+        let at = |hour| point_at(&points, hour).map(|point| point.kilowatt_hours);
+        assert_eq!(at(1.7), Some(1.0));
+        assert_eq!(at(1.2), None);
+    }
+
+    #[test]
+    fn the_title_follows_the_date_format() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert_eq!(chart_title(DateFormat::Iso, date), "Usage for 2024-03-05");
+        assert_eq!(
+            chart_title(DateFormat::MonthDayYear, date),
+            "Usage for 3/5/2024"
+        );
+        assert_eq!(
+            chart_title(DateFormat::DayMonthYear, date),
+            "Usage for 5/3/2024"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! A thin strip across the whole dataset showing which days have data, for finding gaps.
+
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDate};
+
+use super::bookmarks;
+use super::display::DateFormat;
+
+const HEIGHT: f32 = 12.0;
+
+/// Draws the strip from `first` to `last`, shading the days in `daily_totals` and marking
+/// `selected` and any bookmarked days. Returns the day under a click.
+pub fn density_strip_ui(
+    ui: &mut egui::Ui,
+    first: NaiveDate,
+    last: NaiveDate,
+    daily_totals: &BTreeMap<NaiveDate, f64>,
+    bookmarks: &[(NaiveDate, String)],
+    date_format: DateFormat,
+    selected: NaiveDate,
+) -> Option<NaiveDate> {
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), HEIGHT),
+        egui::Sense::click(),
+    );
+    let days = (last - first).num_days() + 1;
+    if days <= 0 {
+        return None;
+    }
+    let day_width = rect.width() / days as f32;
+    let day_left = |day: NaiveDate| rect.left() + (day - first).num_days() as f32 * day_width;
+
+    let visuals = ui.visuals();
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    for &day in daily_totals.keys() {
+        let left = day_left(day);
+        // At least a pixel, so single days still show on long ranges.
+        let segment = egui::Rect::from_x_y_ranges(left..=left + day_width.max(1.0), rect.y_range());
+        painter.rect_filled(segment, 0.0, visuals.selection.bg_fill);
+    }
+    for &(day, _) in bookmarks {
+        if (first..=last).contains(&day) {
+            painter.circle_filled(
+                egui::pos2(day_left(day) + day_width / 2.0, rect.center().y),
+                HEIGHT / 4.0,
+                visuals.warn_fg_color,
+            );
+        }
+    }
+    if (first..=last).contains(&selected) {
+        let x = day_left(selected) + day_width / 2.0;
+        painter.vline(
+            x,
+            rect.y_range(),
+            egui::Stroke::new(2.0, visuals.strong_text_color()),
+        );
+    }
+
+    let pointer_day = |x: f32| {
+        let offset = ((x - rect.left()) / day_width)
+            .floor()
+            .clamp(0.0, (days - 1) as f32);
+        first + Days::new(offset as u64)
+    };
+    if let Some(pointer) = response.hover_pos() {
+        let day = pointer_day(pointer.x);
+        let text = match bookmarks::label(bookmarks, day) {
+            Some(label) => format!("{}  {label}", date_format.format(day)),
+            None => date_format.format(day),
+        };
+        response.clone().on_hover_text(text);
+    }
+    if response.clicked() {
+        return response
+            .interact_pointer_pos()
+            .map(|pointer| pointer_day(pointer.x));
+    }
+    None
+}
@@ -0,0 +1,592 @@
+//! Settings that only change how usage is presented, never the underlying data.
+
+use chrono::NaiveDate;
+
+use super::tags::TagFilter;
+
+/// How the day view is drawn. Persisted along with the rest of the app.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub unit: EnergyUnit,
+    pub y_scale: YScale,
+    /// Share of the day view's height given to the plot, with the rest going to the table.
+    pub plot_height_fraction: f32,
+    pub show_typical_day: bool,
+    pub show_grid: bool,
+    /// Draw plots without their frame and background, for pasting into documents.
+    pub borderless: bool,
+    /// Write each day's total above its bar in the net-by-day chart.
+    pub label_daily_totals: bool,
+    /// Draw each day's hourly profile in its row of the days table. Off skips the painting on
+    /// long ranges.
+    pub show_sparklines: bool,
+    /// Overlay each hour's estimated cost on the day view.
+    pub show_cost: bool,
+    /// Disables pan and zoom so the view can't be knocked out of place while presenting.
+    pub lock_view: bool,
+    /// Show each hour as a percentage of its day's total instead of as energy.
+    pub normalize: bool,
+    /// Sum sub-hourly entries into one bar per hour. Totals always use the fine data.
+    pub roll_up_hourly: bool,
+    /// Bars for less than this much energy are left off the day view to cut clutter.
+    pub min_bar_kilowatt_hours: f64,
+    pub number_format: NumberFormat,
+    pub time_format: TimeFormat,
+    pub date_format: DateFormat,
+    pub bar_anchor: BarAnchor,
+    /// Color each bar along a cool-to-warm gradient by its share of the day's largest bar,
+    /// instead of all in `bar_color`.
+    pub color_by_value: bool,
+    /// Unmultiplied sRGBA of the day's bars.
+    pub bar_color: [u8; 4],
+    pub tags: TagFilter,
+    /// Notes longer than this many characters are cut short in lists, with the rest on hover.
+    pub note_length: usize,
+    /// Only the titled chart is shown, for screenshots and presenting.
+    #[serde(skip)]
+    pub report_mode: bool,
+}
+
+/// egui_plot's first automatic series color, which the bars were always drawn in before they had
+/// a setting.
+fn default_bar_color() -> [u8; 4] {
+    egui::Color32::from(egui::ecolor::Hsva::new(0.0, 0.85, 0.5, 1.0)).to_srgba_unmultiplied()
+}
+
+impl DisplaySettings {
+    pub fn bar_color32(&self) -> egui::Color32 {
+        let [r, g, b, a] = self.bar_color;
+        egui::Color32::from_rgba_unmultiplied(r, g, b, a)
+    }
+}
+
+/// Which point of its interval a bar is centered on.
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum BarAnchor {
+    #[default]
+    Start,
+    Mid,
+    End,
+}
+
+impl BarAnchor {
+    const ALL: [Self; 3] = [Self::Start, Self::Mid, Self::End];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Start => "Start",
+            Self::Mid => "Middle",
+            Self::End => "End",
+        }
+    }
+
+    /// How far into an interval `width` hours long its bar is centered.
+    pub fn offset(self, width: f64) -> f64 {
+        match self {
+            Self::Start => 0.0,
+            Self::Mid => width / 2.0,
+            Self::End => width,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum DateFormat {
+    /// `2025-06-21`, which most tools read without being told the order.
+    #[default]
+    Iso,
+    /// `6/21/2025`, as in the portal's own export.
+    MonthDayYear,
+    /// `21/6/2025`.
+    DayMonthYear,
+}
+
+impl DateFormat {
+    /// Every displayed date goes through this, like [`TimeFormat::format`] for times.
+    pub fn format(self, date: NaiveDate) -> String {
+        match self {
+            Self::Iso => date.format("%Y-%m-%d").to_string(),
+            Self::MonthDayYear => date.format("%-m/%-d/%Y").to_string(),
+            Self::DayMonthYear => date.format("%-d/%-m/%Y").to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum TimeFormat {
+    #[default]
+    TwentyFourHour,
+    TwelveHour,
+}
+
+impl TimeFormat {
+    /// Every displayed time of day goes through this. `hour` may be 24 for the end of the day.
+    pub fn format(self, hour: u8, minute: u8) -> String {
+        match self {
+            Self::TwentyFourHour => format!("{hour:02}:{minute:02}"),
+            Self::TwelveHour => {
+                let hemiday = if (12..24).contains(&hour) { "PM" } else { "AM" };
+                let hour = match hour % 12 {
+                    0 => 12,
+                    hour => hour,
+                };
+                format!("{hour}:{minute:02} {hemiday}")
+            }
+        }
+    }
+
+    /// Formats a time given in hours since midnight, as on the plot's x-axis.
+    pub fn format_hours(self, hours: f64) -> String {
+        let minutes = (hours * 60.0).round() as i64;
+        if !(0..=24 * 60).contains(&minutes) {
+            return String::new();
+        }
+        self.format((minutes / 60) as u8, (minutes % 60) as u8)
+    }
+}
+
+/// How many decimals numbers are shown with. Every displayed number goes through this.
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub energy_decimals: usize,
+    pub cost_decimals: usize,
+    pub currency: Currency,
+    pub grouping: Grouping,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            energy_decimals: 3,
+            cost_decimals: 2,
+            currency: Currency::default(),
+            grouping: Grouping::default(),
+        }
+    }
+}
+
+/// How the digits of large numbers are grouped, and which mark comes before the decimals.
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Grouping {
+    /// `12345.6`, as numbers were always shown and as CSV exports keep them.
+    #[default]
+    None,
+    /// `12,345.6`
+    Comma,
+    /// `12.345,6`
+    Period,
+    /// `12 345,6`, with a narrow no-break space so the groups can't wrap apart.
+    Space,
+}
+
+impl Grouping {
+    const ALL: [Self; 4] = [Self::None, Self::Comma, Self::Period, Self::Space];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::None => "12345.6",
+            Self::Comma => "12,345.6",
+            Self::Period => "12.345,6",
+            Self::Space => "12 345,6",
+        }
+    }
+
+    /// The separator between groups of three digits and the decimal mark.
+    fn separators(self) -> Option<(char, char)> {
+        match self {
+            Self::None => None,
+            Self::Comma => Some((',', '.')),
+            Self::Period => Some(('.', ',')),
+            Self::Space => Some(('\u{202F}', ',')),
+        }
+    }
+
+    /// Regroups `number`, as Rust formats it, with these separators.
+    fn apply(self, number: String) -> String {
+        let Some((separator, decimal_mark)) = self.separators() else {
+            return number;
+        };
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number.as_str()),
+        };
+        let (whole, decimals) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let mut grouped = format!("{sign}{}", group_digits(whole, separator));
+        if !decimals.is_empty() {
+            grouped.push(decimal_mark);
+            grouped.push_str(decimals);
+        }
+        grouped
+    }
+}
+
+/// The symbol costs are shown with. Only the symbol changes; amounts are never converted.
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Currency {
+    #[default]
+    Dollar,
+    Euro,
+    Pound,
+    Yen,
+    Rupee,
+}
+
+impl Currency {
+    const ALL: [Self; 5] = [
+        Self::Dollar,
+        Self::Euro,
+        Self::Pound,
+        Self::Yen,
+        Self::Rupee,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Dollar => "$",
+            Self::Euro => "€",
+            Self::Pound => "£",
+            Self::Yen => "¥",
+            Self::Rupee => "₹",
+        }
+    }
+}
+
+impl NumberFormat {
+    /// An energy value (or anything shown alongside one) without its unit.
+    pub fn energy(self, value: f64) -> String {
+        self.grouping
+            .apply(format!("{value:.*}", self.energy_decimals))
+    }
+
+    pub fn kilowatt_hours(self, kilowatt_hours: f64) -> String {
+        format!("{} kWh", self.energy(kilowatt_hours))
+    }
+
+    /// A cost, with a credit (a negative cost, as net metering can produce) shown with a minus.
+    pub fn cost(self, cost: f64) -> String {
+        // Credits too small to show at this precision are just zero, not "-$0.00".
+        let smallest = 0.5 * 10f64.powi(-(self.cost_decimals as i32));
+        let symbol = self.currency.symbol();
+        let amount = |cost: f64| {
+            self.grouping
+                .apply(format!("{cost:.*}", self.cost_decimals))
+        };
+        if cost <= -smallest {
+            format!("-{symbol}{}", amount(-cost))
+        } else {
+            format!("{symbol}{}", amount(cost.max(0.0)))
+        }
+    }
+}
+
+/// The legend every plot with more than one series uses. Clicking an entry hides its series,
+/// which also leaves it out of the plot's automatic bounds, so series are named for what they
+/// are rather than for the day, keeping them hidden while moving between days.
+pub fn legend() -> egui_plot::Legend {
+    egui_plot::Legend::default().follow_insertion_order(true)
+}
+
+const CREDIT_COLOR: egui::Color32 = egui::Color32::from_rgb(60, 170, 90);
+
+/// Shows `text` about `cost`, in green when the cost is a credit.
+pub fn cost_label(ui: &mut egui::Ui, cost: f64, text: String) -> egui::Response {
+    if cost < 0.0 {
+        ui.colored_label(CREDIT_COLOR, text)
+    } else {
+        ui.label(text)
+    }
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            unit: EnergyUnit::default(),
+            y_scale: YScale::default(),
+            plot_height_fraction: 0.7,
+            show_typical_day: false,
+            show_grid: false,
+            borderless: false,
+            label_daily_totals: false,
+            show_sparklines: true,
+            show_cost: false,
+            lock_view: false,
+            normalize: false,
+            roll_up_hourly: false,
+            min_bar_kilowatt_hours: 0.0,
+            number_format: NumberFormat::default(),
+            time_format: TimeFormat::default(),
+            date_format: DateFormat::default(),
+            bar_anchor: BarAnchor::default(),
+            color_by_value: false,
+            bar_color: default_bar_color(),
+            tags: TagFilter::default(),
+            note_length: 80,
+            report_mode: false,
+        }
+    }
+}
+
+pub fn display_settings_ui(ui: &mut egui::Ui, settings: &mut DisplaySettings) {
+    egui::ComboBox::from_label("Unit")
+        .selected_text(settings.unit.label())
+        .show_ui(ui, |ui| {
+            for unit in EnergyUnit::ALL {
+                ui.selectable_value(&mut settings.unit, unit, unit.label());
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut settings.y_scale, YScale::Linear, "Linear");
+        ui.radio_value(&mut settings.y_scale, YScale::Log, "Log");
+    });
+    ui.checkbox(&mut settings.normalize, "Percent of daily total");
+    ui.checkbox(&mut settings.roll_up_hourly, "Roll up to hourly");
+    ui.horizontal(|ui| {
+        ui.label("Hide bars below");
+        ui.add(
+            egui::DragValue::new(&mut settings.min_bar_kilowatt_hours)
+                .speed(0.001)
+                .range(0.0..=f64::INFINITY)
+                .suffix(" kWh"),
+        );
+    });
+    ui.checkbox(&mut settings.show_grid, "Show grid");
+    ui.checkbox(&mut settings.borderless, "Borderless plots");
+    ui.checkbox(&mut settings.label_daily_totals, "Label daily totals");
+    ui.checkbox(
+        &mut settings.show_sparklines,
+        "Sparklines in the days table",
+    );
+    ui.horizontal(|ui| {
+        ui.label("Center bars on interval");
+        for anchor in BarAnchor::ALL {
+            ui.radio_value(&mut settings.bar_anchor, anchor, anchor.label());
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Bar color");
+        ui.color_edit_button_srgba_unmultiplied(&mut settings.bar_color);
+        if ui.small_button("Reset").clicked() {
+            settings.bar_color = default_bar_color();
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.radio_value(
+            &mut settings.time_format,
+            TimeFormat::TwentyFourHour,
+            "24-hour",
+        );
+        ui.radio_value(&mut settings.time_format, TimeFormat::TwelveHour, "12-hour");
+    });
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut settings.date_format, DateFormat::Iso, "YYYY-MM-DD");
+        ui.radio_value(
+            &mut settings.date_format,
+            DateFormat::MonthDayYear,
+            "M/D/YYYY",
+        );
+        ui.radio_value(
+            &mut settings.date_format,
+            DateFormat::DayMonthYear,
+            "D/M/YYYY",
+        );
+    });
+    ui.add(egui::Slider::new(&mut settings.plot_height_fraction, 0.2..=1.0).text("Plot height"));
+    egui::ComboBox::from_label("Currency")
+        .selected_text(settings.number_format.currency.symbol())
+        .show_ui(ui, |ui| {
+            for currency in Currency::ALL {
+                ui.selectable_value(
+                    &mut settings.number_format.currency,
+                    currency,
+                    currency.symbol(),
+                );
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.label("Shorten notes to");
+        ui.add(
+            egui::DragValue::new(&mut settings.note_length)
+                .range(10..=1000)
+                .suffix(" characters"),
+        );
+    });
+    ui.horizontal(|ui| {
+        ui.label("Decimals: energy");
+        ui.add(egui::DragValue::new(&mut settings.number_format.energy_decimals).range(0..=6));
+        ui.label("cost");
+        ui.add(egui::DragValue::new(&mut settings.number_format.cost_decimals).range(0..=6));
+    });
+    egui::ComboBox::from_label("Digit grouping")
+        .selected_text(settings.number_format.grouping.label())
+        .show_ui(ui, |ui| {
+            for grouping in Grouping::ALL {
+                ui.selectable_value(
+                    &mut settings.number_format.grouping,
+                    grouping,
+                    grouping.label(),
+                );
+            }
+        });
+}
+
+/// `text` cut to at most `max_chars` characters, ending in an ellipsis if anything was cut.
+pub fn truncate(text: &str, max_chars: usize) -> std::borrow::Cow<'_, str> {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]).into(),
+        None => text.into(),
+    }
+}
+
+/// Writes a count with commas between groups of three digits, like `35,040`.
+pub fn group_thousands(count: usize) -> String {
+    group_digits(&count.to_string(), ',')
+}
+
+/// `digits` with `separator` between groups of three, counting from the right.
+fn group_digits(digits: &str, separator: char) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * 3);
+    // A group starts wherever the digits left to go are a multiple of three.
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && i % 3 == digits.len() % 3 {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Smallest value shown on a logarithmic axis. Zero and negative readings have no logarithm, so
+/// they are clamped up to this instead.
+const LOG_FLOOR: f64 = 1e-3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum EnergyUnit {
+    #[default]
+    KilowattHours,
+    WattHours,
+}
+
+impl EnergyUnit {
+    pub const ALL: [Self; 2] = [Self::KilowattHours, Self::WattHours];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::KilowattHours => "kWh",
+            Self::WattHours => "Wh",
+        }
+    }
+
+    pub fn convert(self, kilowatt_hours: f64) -> f64 {
+        match self {
+            Self::KilowattHours => kilowatt_hours,
+            Self::WattHours => kilowatt_hours * 1000.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum YScale {
+    #[default]
+    Linear,
+    Log,
+}
+
+impl YScale {
+    /// Where bars start from, in plot coordinates.
+    pub fn base(self) -> f64 {
+        self.to_plot(0.0)
+    }
+
+    /// Maps a value onto the plot's y-axis.
+    pub fn to_plot(self, value: f64) -> f64 {
+        match self {
+            Self::Linear => value,
+            Self::Log => value.max(LOG_FLOOR).log10(),
+        }
+    }
+
+    /// Inverse of [`Self::to_plot`], for labelling the axis and tooltips.
+    pub fn to_value(self, y: f64) -> f64 {
+        match self {
+            Self::Linear => y,
+            Self::Log => 10f64.powf(y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digits_are_grouped_in_threes_from_the_right() {
+        assert_eq!(group_digits("", ','), "");
+        assert_eq!(group_digits("999", ','), "999");
+        assert_eq!(group_digits("1000", ','), "1,000");
+        assert_eq!(group_digits("1234567", '.'), "1.234.567");
+        assert_eq!(group_thousands(12345), "12,345");
+    }
+
+    #[test]
+    fn grouping_keeps_the_sign_and_swaps_the_decimal_mark() {
+        assert_eq!(Grouping::None.apply("-12345.6".to_owned()), "-12345.6");
+        assert_eq!(Grouping::Comma.apply("-12345.6".to_owned()), "-12,345.6");
+        assert_eq!(Grouping::Period.apply("12345.6".to_owned()), "12.345,6");
+        assert_eq!(Grouping::Space.apply("1234".to_owned()), "1\u{202F}234");
+    }
+
+    #[test]
+    fn long_notes_are_cut_for_display_only() {
+        let note = format!("Estimated: {}", "é".repeat(200));
+        let data = crate::app::UsageData::from_rows(&format!(
+            "Electric usage,1/15/2024,1:00 AM,2:00 AM,0.42,kWh,{note}\n"
+        ));
+        let entry = &data.entries[0];
+        let shown = truncate(entry.note(), 20);
+        assert_eq!(shown.chars().count(), 21);
+        assert!(shown.starts_with("Estimated: éé") && shown.ends_with('…'));
+        assert_eq!(entry.note(), note);
+        assert!(matches!(
+            truncate("short", 20),
+            std::borrow::Cow::Borrowed("short")
+        ));
+    }
+
+    #[test]
+    fn grouped_costs_and_credits() {
+        let format = NumberFormat {
+            grouping: Grouping::Comma,
+            ..NumberFormat::default()
+        };
+        assert_eq!(format.cost(1234.5), "$1,234.50");
+        assert_eq!(format.cost(-1234.5), "-$1,234.50");
+        assert_eq!(format.cost(-0.001), "$0.00");
+        assert_eq!(format.energy(1234.5), "1,234.500");
+    }
+
+    #[test]
+    fn every_grouping_formats_energy_and_cost() {
+        let format = |grouping| NumberFormat {
+            grouping,
+            currency: Currency::Euro,
+            ..NumberFormat::default()
+        };
+        let cases = [
+            (Grouping::None, "1234567.891", "€1234567.89"),
+            (Grouping::Comma, "1,234,567.891", "€1,234,567.89"),
+            (Grouping::Period, "1.234.567,891", "€1.234.567,89"),
+            (
+                Grouping::Space,
+                "1\u{202F}234\u{202F}567,891",
+                "€1\u{202F}234\u{202F}567,89",
+            ),
+        ];
+        for (grouping, energy, cost) in cases {
+            assert_eq!(format(grouping).energy(1234567.891), energy);
+            assert_eq!(format(grouping).cost(1234567.891), cost);
+        }
+        assert_eq!(format(Grouping::Period).energy(-999.5), "-999,500");
+    }
+}
@@ -0,0 +1,289 @@
+//! Estimated CO₂ emissions of the usage, from a flat emissions factor per kWh or, with a grid
+//! carbon-intensity file imported, from how clean the grid was in each hour.
+//!
+//! The flat factor is whatever the utility or grid operator publishes for the area. Either way
+//! emissions only scale energy, so exports count as avoided emissions.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, ensure, Context};
+use chrono::{Days, NaiveDate, NaiveDateTime, Timelike};
+
+use super::display::{DisplaySettings, NumberFormat};
+use super::range::DateRange;
+use super::{timestamp_csv, DayFilter, OnLine, UsageData};
+
+/// Roughly a mixed grid's average, in kg CO₂ per kWh, until the user sets their own.
+pub const DEFAULT_FACTOR: f64 = 0.4;
+
+/// kg CO₂ for `kilowatt_hours` at `factor` kg per kWh. Net exports come out negative.
+pub fn emissions(kilowatt_hours: f64, factor: f64) -> f64 {
+    kilowatt_hours * factor
+}
+
+pub fn kilograms(number_format: NumberFormat, kilograms: f64) -> String {
+    format!("{} kg CO₂", number_format.energy(kilograms))
+}
+
+/// How carbon-intensive the grid was in each hour, from a `timestamp,gCO₂/kWh` file.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct GridIntensity {
+    /// kg CO₂ per kWh, keyed by the start of each hour. Files with finer rows are averaged into
+    /// their hour.
+    hours: BTreeMap<NaiveDateTime, f64>,
+    /// The mean over every hour, which hours the file doesn't cover fall back to.
+    average: f64,
+}
+
+impl GridIntensity {
+    /// Parses rows of an ISO 8601 timestamp and an intensity in grams of CO₂ per kWh, allowing a
+    /// header row.
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut sums: BTreeMap<NaiveDateTime, (f64, usize)> = BTreeMap::new();
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let row = (|| {
+                let Some((timestamp, grams)) = line.split_once(',') else {
+                    bail!("Expected two columns");
+                };
+                let Ok(grams) = grams.trim().parse::<f64>() else {
+                    ensure!(sums.is_empty(), "Invalid intensity {grams:?}");
+                    return Ok(None);
+                };
+                let hour = timestamp_csv::parse_timestamp(timestamp.trim())?
+                    .with_minute(0)
+                    .and_then(|time| time.with_second(0))
+                    .context("Invalid timestamp")?;
+                Ok(Some((hour, grams / 1000.0)))
+            })()
+            .context(OnLine(i + 1))?;
+            if let Some((hour, intensity)) = row {
+                let sum = sums.entry(hour).or_insert((0.0, 0));
+                sum.0 += intensity;
+                sum.1 += 1;
+            }
+        }
+        ensure!(!sums.is_empty(), "No intensity readings in the file");
+
+        let hours = sums
+            .into_iter()
+            .map(|(hour, (sum, count))| (hour, sum / count as f64))
+            .collect::<BTreeMap<_, _>>();
+        let average = hours.values().sum::<f64>() / hours.len() as f64;
+        Ok(Self { hours, average })
+    }
+
+    /// kg CO₂ per kWh in the hour containing `time`, or the file's average if it skips that hour.
+    fn factor_at(&self, time: NaiveDateTime) -> f64 {
+        time.with_minute(0)
+            .and_then(|hour| self.hours.get(&hour))
+            .copied()
+            .unwrap_or(self.average)
+    }
+}
+
+/// Each day's emissions, worked out once rather than every frame they're shown.
+pub struct DailyEmissions {
+    /// The flat factor they were worked out with; with an intensity file it only matters when
+    /// that's cleared.
+    factor: f64,
+    pub days: BTreeMap<NaiveDate, f64>,
+}
+
+impl DailyEmissions {
+    /// Sums every included entry's emissions into its day, at the hour's intensity if there is
+    /// an intensity file and at `factor` otherwise.
+    pub fn new(data: &UsageData, intensity: Option<&GridIntensity>, factor: f64) -> Self {
+        let mut days = BTreeMap::new();
+        for entry in data.included() {
+            let factor = intensity.map_or(factor, |intensity| intensity.factor_at(entry.start()));
+            *days.entry(entry.day()).or_insert(0.0) += emissions(entry.kilowatt_hours, factor);
+        }
+        Self { factor, days }
+    }
+
+    pub fn is_for(&self, factor: f64) -> bool {
+        self.factor == factor
+    }
+
+    /// Total emissions of the days from `start` on that `include` accepts.
+    pub fn total(&self, start: NaiveDate, include: impl Fn(NaiveDate) -> bool) -> f64 {
+        self.days
+            .range(start..)
+            .filter(|(&day, _)| include(day))
+            .map(|(_, emissions)| emissions)
+            .sum()
+    }
+}
+
+/// The flat factor, and importing or clearing an intensity file. Returns whether either changed,
+/// so emissions can be worked out again.
+pub fn emissions_settings_ui(
+    ui: &mut egui::Ui,
+    factor: &mut f64,
+    intensity: &mut Option<GridIntensity>,
+) -> anyhow::Result<bool> {
+    let mut changed = false;
+    ui.add_enabled_ui(intensity.is_none(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Emissions factor");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(factor)
+                        .speed(0.005)
+                        .range(0.0..=2.0)
+                        .suffix(" kg CO₂/kWh"),
+                )
+                .changed();
+        });
+    });
+    let mut import = false;
+    ui.horizontal(|ui| match intensity {
+        Some(loaded) => {
+            ui.label(format!("Grid intensity for {} hours", loaded.hours.len()));
+            if ui.small_button("Clear").clicked() {
+                *intensity = None;
+                changed = true;
+            }
+        }
+        None => import = ui.button("Import grid intensity…").clicked(),
+    });
+    if import {
+        if let Some(path) = rfd::FileDialog::new().pick_file() {
+            let input = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            *intensity = Some(GridIntensity::parse(&input)?);
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+/// Each day's emissions across `range`, for the days `filter` keeps. Returns the day whose bar
+/// was clicked.
+pub fn emissions_ui(
+    ui: &mut egui::Ui,
+    emissions: &DailyEmissions,
+    filter: &DayFilter,
+    range: DateRange,
+    settings: &DisplaySettings,
+) -> Option<NaiveDate> {
+    let start = range.start;
+    let bars = emissions
+        .days
+        .iter()
+        .filter(|(&day, _)| (range.start..=range.end).contains(&day) && filter.includes(day))
+        .map(|(&day, &kilograms)| {
+            egui_plot::Bar::new((day - start).num_days() as f64, kilograms).width(1.0)
+        })
+        .collect::<Vec<_>>();
+    if bars.is_empty() {
+        ui.label("No usage in the selected range.");
+        return None;
+    }
+
+    let date_format = settings.date_format;
+    let number_format = settings.number_format;
+    let day_at = move |x: f64| {
+        u64::try_from(x.round() as i64)
+            .ok()
+            .and_then(|offset| start.checked_add_days(Days::new(offset)))
+    };
+    let label = move |x: f64| {
+        day_at(x)
+            .map(|day| date_format.format(day))
+            .unwrap_or_default()
+    };
+    let mut clicked = None;
+    egui_plot::Plot::new("emissions_plot")
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label("Emissions (kg CO₂)")
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| label(mark.value))
+        .show(ui, |plot_ui| {
+            if plot_ui.response().clicked() {
+                clicked = plot_ui
+                    .pointer_coordinate()
+                    .and_then(|pointer| day_at(pointer.x));
+            }
+            plot_ui.bar_chart(
+                egui_plot::BarChart::new("Emissions", bars)
+                    .color(settings.bar_color32())
+                    .element_formatter(Box::new(move |bar, _chart| {
+                        format!(
+                            "{}\n{}",
+                            label(bar.argument),
+                            kilograms(number_format, bar.value)
+                        )
+                    })),
+            );
+        });
+    clicked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USAGE: &str = "\
+Electric usage,1/15/2024,7:00 AM,8:00 AM,2.0,kWh,
+Electric usage,1/15/2024,12:00 PM,1:00 PM,-1.0,kWh,
+Electric usage,1/16/2024,7:00 AM,8:00 AM,3.0,kWh,
+";
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn a_flat_factor_scales_energy() {
+        assert!((emissions(10.0, 0.4) - 4.0).abs() < 1e-9);
+        assert!((emissions(-2.5, 0.4) + 1.0).abs() < 1e-9);
+        assert_eq!(kilograms(NumberFormat::default(), 1.25), "1.250 kg CO₂");
+    }
+
+    #[test]
+    fn daily_emissions_at_a_flat_factor() {
+        let mut data = UsageData::from_rows(USAGE);
+        data.entries[2].excluded = true;
+        let emissions = DailyEmissions::new(&data, None, 0.5);
+        assert!(emissions.is_for(0.5) && !emissions.is_for(0.4));
+        // The export offsets part of the first day.
+        assert_eq!(emissions.days.get(&day(15)), Some(&0.5));
+        assert_eq!(emissions.days.get(&day(16)), None);
+        assert_eq!(emissions.total(day(1), |_| true), 0.5);
+        assert_eq!(emissions.total(day(16), |_| true), 0.0);
+    }
+
+    #[test]
+    fn hourly_intensity_from_a_file() {
+        let intensity =
+            GridIntensity::parse(include_str!("../../tests/fixtures/grid_intensity.csv")).unwrap();
+        // The two half-hour rows at 7:00 are averaged into their hour.
+        assert_eq!(intensity.hours.len(), 3);
+        let average = (0.4 + 0.2 + 0.1) / 3.0;
+        assert!((intensity.average - average).abs() < 1e-9);
+
+        let mut rows = USAGE.to_owned();
+        rows.push_str("Electric usage,1/16/2024,8:00 AM,9:00 AM,1.0,kWh,\n");
+        let data = UsageData::from_rows(&rows);
+        let emissions = DailyEmissions::new(&data, Some(&intensity), DEFAULT_FACTOR);
+        // 2 kWh at 0.4 kg/kWh, less 1 kWh exported at 0.2.
+        assert!((emissions.days[&day(15)] - 0.6).abs() < 1e-9);
+        // 3 kWh at 0.1, and an hour the file doesn't cover at its average.
+        assert!((emissions.days[&day(16)] - (0.3 + average)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_bad_intensity_row_fails_with_its_line() {
+        let Err(error) = GridIntensity::parse("2024-01-15T07:00:00,300\nsoon,200\n") else {
+            panic!("a bad timestamp should fail the file");
+        };
+        assert_eq!(error.downcast_ref::<OnLine>().map(|line| line.0), Some(2));
+        assert!(GridIntensity::parse("timestamp,intensity\n").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! An arc showing the current billing cycle's usage against the previous cycle's.
+
+use std::f32::consts::PI;
+
+use super::display::NumberFormat;
+
+/// How far past the reference the arc can read, as a multiple of it.
+const RANGE: f64 = 1.5;
+const SEGMENTS: usize = 48;
+
+/// Points along an arc from `start` to `end` (radians, clockwise from the left).
+fn arc_points(center: egui::Pos2, radius: f32, start: f32, end: f32) -> Vec<egui::Pos2> {
+    (0..=SEGMENTS)
+        .map(|i| {
+            let angle = PI + start + (end - start) * i as f32 / SEGMENTS as f32;
+            center + radius * egui::vec2(angle.cos(), angle.sin())
+        })
+        .collect()
+}
+
+/// Draws the gauge, with a tick at the previous cycle's total. Colors come from the current
+/// theme, with the warning color once the current cycle has passed the previous one.
+pub fn cycle_gauge_ui(ui: &mut egui::Ui, current: f64, previous: f64, number_format: NumberFormat) {
+    let size = egui::vec2(160.0, 90.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let rect = response.rect;
+    let center = egui::pos2(rect.center().x, rect.bottom() - 10.0);
+    let radius = 70.0;
+    let width = 12.0;
+    let visuals = ui.visuals();
+
+    let full = PI;
+    let fraction = |value: f64| (value / (previous * RANGE)).clamp(0.0, 1.0) as f32;
+    painter.add(egui::Shape::line(
+        arc_points(center, radius, 0.0, full),
+        egui::Stroke::new(width, visuals.widgets.inactive.bg_fill),
+    ));
+    let color = if current > previous {
+        visuals.warn_fg_color
+    } else {
+        visuals.selection.bg_fill
+    };
+    painter.add(egui::Shape::line(
+        arc_points(center, radius, 0.0, full * fraction(current)),
+        egui::Stroke::new(width, color),
+    ));
+
+    let reference = PI + full * fraction(previous);
+    let direction = egui::vec2(reference.cos(), reference.sin());
+    painter.line_segment(
+        [
+            center + (radius - width) * direction,
+            center + (radius + width) * direction,
+        ],
+        egui::Stroke::new(2.0, visuals.strong_text_color()),
+    );
+    painter.text(
+        center - egui::vec2(0.0, 12.0),
+        egui::Align2::CENTER_CENTER,
+        format!("{:.0}%", current / previous * 100.0),
+        egui::FontId::proportional(18.0),
+        visuals.text_color(),
+    );
+
+    response.on_hover_text(format!(
+        "This cycle: {}\nLast cycle: {}",
+        number_format.kilowatt_hours(current),
+        number_format.kilowatt_hours(previous)
+    ));
+}
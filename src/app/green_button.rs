@@ -0,0 +1,154 @@
+//! Parser for Green Button (ESPI) XML downloads.
+//!
+//! Only the parts needed to build a [`UsageData`] are read: the feed title (used as the address),
+//! the reading type's unit and multiplier, the local time offset, and every `IntervalReading`.
+
+use anyhow::{bail, ensure, Context};
+use chrono::{DateTime, TimeDelta};
+use quick_xml::events::Event;
+
+use super::{UsageData, UsageEntry};
+
+/// ESPI unit-of-measure code for watt-hours.
+const UOM_WATT_HOURS: u32 = 72;
+
+pub fn parse_green_button(xml: &str) -> anyhow::Result<UsageData> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut address = None;
+    let mut uom = UOM_WATT_HOURS;
+    let mut power_of_ten_multiplier = 0;
+    let mut tz_offset = 0;
+    // (start, duration, value) for each reading, still in raw units.
+    let mut readings = Vec::new();
+    let mut reading = (None, None, None);
+
+    loop {
+        match reader.read_event().context("Invalid XML")? {
+            Event::Start(element) => {
+                path.push(String::from_utf8_lossy(element.local_name().as_ref()).into_owned());
+            }
+            Event::End(element) => {
+                if element.local_name().as_ref() == b"IntervalReading" {
+                    let (Some(start), Some(duration), Some(value)) = reading else {
+                        bail!("IntervalReading is missing its start, duration, or value");
+                    };
+                    readings.push((start, duration, value));
+                    reading = (None, None, None);
+                }
+                path.pop();
+            }
+            Event::Text(text) => {
+                let text = text.unescape().context("Invalid XML text")?;
+                let in_reading = path.iter().any(|name| name == "IntervalReading");
+                let parent = path.len().checked_sub(2).map(|i| path[i].as_str());
+                match path.last().map(String::as_str) {
+                    Some("title") if address.is_none() => address = Some(text.into_owned()),
+                    Some("uom") => uom = text.parse().context("Invalid uom")?,
+                    Some("powerOfTenMultiplier") => {
+                        power_of_ten_multiplier =
+                            text.parse().context("Invalid powerOfTenMultiplier")?
+                    }
+                    Some("tzOffset") => tz_offset = text.parse().context("Invalid tzOffset")?,
+                    Some("start") if in_reading => {
+                        reading.0 = Some(text.parse::<i64>().context("Invalid start")?)
+                    }
+                    Some("duration") if in_reading => {
+                        reading.1 = Some(text.parse::<i64>().context("Invalid duration")?)
+                    }
+                    Some("value") if parent == Some("IntervalReading") => {
+                        reading.2 = Some(text.parse::<f64>().context("Invalid value")?)
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    ensure!(
+        uom == UOM_WATT_HOURS,
+        "Unsupported unit of measure {uom}, only watt-hours (72) are supported"
+    );
+    let scale = 10f64.powi(power_of_ten_multiplier) / 1000.0;
+
+    let entries = readings
+        .into_iter()
+        .map(|(start, duration, value)| {
+            let local = DateTime::from_timestamp(start + tz_offset, 0)
+                .with_context(|| format!("Invalid start time {start}"))?
+                .naive_utc();
+            UsageEntry::from_interval(local, TimeDelta::seconds(duration), value * scale)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(UsageData {
+        address: address.unwrap_or_default(),
+        meters: Vec::new(),
+        entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ParseOptions;
+
+    const SAMPLE: &str = include_str!("../../tests/fixtures/green_button.xml");
+
+    #[test]
+    fn reads_interval_readings_in_local_time() {
+        let data = parse_green_button(SAMPLE).unwrap();
+        assert_eq!(data.address, "123 Example St & Co");
+        assert_eq!(data.entries.len(), 2);
+
+        let first = &data.entries[0];
+        assert_eq!(first.date, (2024, 1, 1));
+        assert_eq!(
+            (first.interval_start_hour, first.interval_start_minute),
+            (0, 0)
+        );
+        assert_eq!((first.interval_end_hour, first.interval_end_minute), (1, 0));
+        assert!((first.kilowatt_hours - 0.42).abs() < 1e-9);
+
+        let second = &data.entries[1];
+        assert_eq!(
+            (second.interval_start_hour, second.interval_end_hour),
+            (1, 2)
+        );
+        assert!((second.kilowatt_hours - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn applies_the_power_of_ten_multiplier() {
+        let xml = SAMPLE.replace(
+            "<espi:powerOfTenMultiplier>0<",
+            "<espi:powerOfTenMultiplier>-3<",
+        );
+        let data = parse_green_button(&xml).unwrap();
+        assert!((data.entries[0].kilowatt_hours - 0.00042).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_units_other_than_watt_hours() {
+        let xml = SAMPLE.replace("<espi:uom>72<", "<espi:uom>38<");
+        assert!(parse_green_button(&xml).is_err());
+    }
+
+    #[test]
+    fn is_detected_by_extension_or_content() {
+        let options = ParseOptions {
+            lenient: false,
+            skip_totals_rows: true,
+        };
+        for path in ["usage.xml", "usage.txt"] {
+            let (data, skipped) =
+                UsageData::parse_file(std::path::Path::new(path), SAMPLE, options).unwrap();
+            assert_eq!(data.entries.len(), 2);
+            assert!(skipped.is_empty());
+        }
+    }
+}
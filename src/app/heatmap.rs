@@ -0,0 +1,70 @@
+//! A weekday × hour grid of average usage, showing when in the week the most energy is used.
+
+use super::display::DisplaySettings;
+
+const CELL: egui::Vec2 = egui::vec2(24.0, 20.0);
+const LABEL_WIDTH: f32 = 40.0;
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const HIGH: egui::Color32 = egui::Color32::from_rgb(220, 60, 40);
+
+pub fn week_hours_ui(ui: &mut egui::Ui, averages: &[[f64; 24]; 7], settings: &DisplaySettings) {
+    let peak = averages.iter().flatten().copied().fold(0.0, f64::max);
+    let size = egui::vec2(LABEL_WIDTH + CELL.x * 24.0, CELL.y * 8.0);
+    let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+    let origin = response.rect.min;
+    let visuals = ui.visuals();
+    let font = egui::FontId::proportional(11.0);
+
+    for hour in (0..24).step_by(3) {
+        painter.text(
+            origin + egui::vec2(LABEL_WIDTH + CELL.x * hour as f32, 0.0),
+            egui::Align2::LEFT_TOP,
+            settings.time_format.format(hour, 0),
+            font.clone(),
+            visuals.text_color(),
+        );
+    }
+
+    let mut hovered = None;
+    for (weekday, hours) in averages.iter().enumerate() {
+        let top = origin.y + CELL.y * (weekday + 1) as f32;
+        painter.text(
+            egui::pos2(origin.x, top + CELL.y / 2.0),
+            egui::Align2::LEFT_CENTER,
+            WEEKDAYS[weekday],
+            font.clone(),
+            visuals.text_color(),
+        );
+        for (hour, &average) in hours.iter().enumerate() {
+            let cell = egui::Rect::from_min_size(
+                egui::pos2(origin.x + LABEL_WIDTH + CELL.x * hour as f32, top),
+                CELL,
+            )
+            .shrink(1.0);
+            let t = if peak > 0.0 { average / peak } else { 0.0 };
+            painter.rect_filled(
+                cell,
+                2.0,
+                visuals.faint_bg_color.lerp_to_gamma(HIGH, t as f32),
+            );
+            if response
+                .hover_pos()
+                .is_some_and(|pointer| cell.contains(pointer))
+            {
+                hovered = Some((weekday, hour, average));
+            }
+        }
+    }
+
+    if let Some((weekday, hour, average)) = hovered {
+        response.on_hover_text(format!(
+            "{} {}\n{} {} on average",
+            WEEKDAYS[weekday],
+            settings.time_format.format(hour as u8, 0),
+            settings
+                .number_format
+                .energy(settings.unit.convert(average)),
+            settings.unit.label()
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+//! Upgrading state saved by older versions of the app.
+//!
+//! Every change to what's persisted that old state can't simply default its way through gets a
+//! new version here, along with the step that brings the previous version's state up to it.
+
+use super::{TemplateApp, UsageData};
+
+/// The version state is saved as. State from before versions were recorded reads as 0.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Brings `app` up to [`CURRENT_VERSION`] one step at a time. Returns a warning to show if the
+/// state couldn't be fully carried over.
+pub fn migrate(app: &mut TemplateApp) -> Option<String> {
+    if app.version > CURRENT_VERSION {
+        let warning = format!(
+            "Settings were saved by a newer version of the app (version {}), so some may have been lost",
+            app.version
+        );
+        app.version = CURRENT_VERSION;
+        return Some(warning);
+    }
+    if app.version == 0 {
+        // Intervals ending at midnight used to be stored as ending at hour 0.
+        if let Some(data) = &mut app.data {
+            fix_midnight_ends(data);
+        }
+        for dataset in &mut app.comparisons {
+            fix_midnight_ends(&mut dataset.data);
+        }
+        app.version = 1;
+    }
+    if app.version == 1 {
+        // Notes used to be stored as plain strings, empty for no note. Those can't be read as
+        // optional notes at all, so it's done while the entries are read, in `deserialize_note`.
+        app.version = 2;
+    }
+    None
+}
+
+fn fix_midnight_ends(data: &mut UsageData) {
+    for entry in &mut data.entries {
+        let starts_at_midnight = (entry.interval_start_hour, entry.interval_start_minute) == (0, 0);
+        if (entry.interval_end_hour, entry.interval_end_minute) == (0, 0) && !starts_at_midnight {
+            entry.interval_end_hour = 24;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saved state from `version`, with one entry of a comparison and of `data`: the hour up to
+    /// midnight, saved as ending at `end_hour`, with an empty note.
+    fn saved_state(version: Option<u32>, end_hour: u8) -> TemplateApp {
+        let entries = format!(
+            "[(date: (2024, 1, 15), interval_start_hour: 23, interval_end_hour: {end_hour}, \
+             kilowatt_hours: 0.51, note: \"\")]"
+        );
+        let version = version.map(|version| format!("version: {version}, "));
+        let ron = format!(
+            "({}data: Some((address: \"123 Example St\", entries: {entries})), \
+             comparisons: [(label: \"Old\", color: (1, 2, 3, 255), \
+             data: (address: \"\", entries: {entries}))])",
+            version.unwrap_or_default(),
+        );
+        ron::from_str(&ron).unwrap()
+    }
+
+    fn entries(app: &TemplateApp) -> impl Iterator<Item = &crate::app::UsageEntry> {
+        app.data
+            .iter()
+            .chain(app.comparisons.iter().map(|dataset| &dataset.data))
+            .flat_map(|data| &data.entries)
+    }
+
+    #[test]
+    fn version_0_state_is_upgraded() {
+        let mut app = saved_state(None, 0);
+        assert_eq!(app.version, 0);
+        assert_eq!(migrate(&mut app), None);
+        assert_eq!(app.version, CURRENT_VERSION);
+        assert_eq!(entries(&app).count(), 2);
+        for entry in entries(&app) {
+            assert_eq!(entry.interval_end_hour, 24);
+            assert_eq!(entry.note, None);
+        }
+    }
+
+    #[test]
+    fn version_1_state_is_upgraded() {
+        let mut app = saved_state(Some(1), 24);
+        assert_eq!(migrate(&mut app), None);
+        assert_eq!(app.version, CURRENT_VERSION);
+        for entry in entries(&app) {
+            assert_eq!(entry.interval_end_hour, 24);
+            assert_eq!(entry.note, None);
+        }
+    }
+
+    #[test]
+    fn state_from_a_newer_version_warns() {
+        let mut app = saved_state(Some(CURRENT_VERSION + 1), 24);
+        let warning = migrate(&mut app).unwrap();
+        assert!(warning.contains("newer version"));
+        assert_eq!(app.version, CURRENT_VERSION);
+        assert_eq!(entries(&app).count(), 2);
+    }
+}
@@ -0,0 +1,220 @@
+//! Compact whole-dataset views used to pick a day for the detailed chart.
+
+use chrono::{Datelike, Days, NaiveDate};
+
+use super::display::{self, DateFormat, DisplaySettings, NumberFormat};
+use super::{DayFilter, UsageData};
+
+/// Draws one small bar chart of daily totals per month. Returns the first day of a month whose
+/// chart was clicked.
+pub fn month_grid_ui(ui: &mut egui::Ui, data: &UsageData, filter: &DayFilter) -> Option<NaiveDate> {
+    const COLUMNS: usize = 4;
+
+    let mut clicked = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("month_grid").show(ui, |ui| {
+            for (i, ((year, month), totals)) in
+                data.monthly_daily_totals(filter).into_iter().enumerate()
+            {
+                let Some(first_day) = totals.keys().next().copied() else {
+                    continue;
+                };
+                ui.vertical(|ui| {
+                    ui.label(first_day.format("%B %Y").to_string());
+                    let response = egui_plot::Plot::new(("month_plot", year, month))
+                        .width(200.0)
+                        .height(100.0)
+                        .show_axes(false)
+                        .show_grid(false)
+                        .allow_drag(false)
+                        .allow_zoom(false)
+                        .allow_scroll(false)
+                        .allow_boxed_zoom(false)
+                        .allow_double_click_reset(false)
+                        .include_x(0.5)
+                        .include_x(31.5)
+                        .include_y(0.0)
+                        .show(ui, |plot_ui| {
+                            plot_ui.bar_chart(egui_plot::BarChart::new(
+                                first_day.format("%B %Y").to_string(),
+                                totals
+                                    .iter()
+                                    .map(|(day, total)| {
+                                        egui_plot::Bar::new(day.day() as f64, *total).width(1.0)
+                                    })
+                                    .collect::<Vec<_>>(),
+                            ));
+                        })
+                        .response;
+                    if response.clicked() {
+                        clicked = Some(first_day);
+                    }
+                });
+                if (i + 1) % COLUMNS == 0 {
+                    ui.end_row();
+                }
+            }
+        });
+    });
+    clicked
+}
+
+/// The day `x` days after `first`, as plotted by [`daily_plot`].
+fn day_at(first: NaiveDate, x: f64) -> Option<NaiveDate> {
+    let offset = u64::try_from(x.round() as i64).ok()?;
+    first.checked_add_days(Days::new(offset))
+}
+
+fn date_label(first: NaiveDate, date_format: DateFormat, x: f64) -> String {
+    day_at(first, x)
+        .map(|day| date_format.format(day))
+        .unwrap_or_default()
+}
+
+/// A plot with a bar per day, placed on days since `first` so the axis can label them with
+/// dates.
+fn daily_plot(
+    id: &str,
+    y_label: &str,
+    first: NaiveDate,
+    settings: &DisplaySettings,
+) -> egui_plot::Plot<'static> {
+    let date_format = settings.date_format;
+    egui_plot::Plot::new(id)
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label(y_label)
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| date_label(first, date_format, mark.value))
+}
+
+/// The day of the bar clicked this frame, if it has data in `days` (in date order).
+fn clicked_day(
+    plot_ui: &egui_plot::PlotUi<'_>,
+    first: NaiveDate,
+    days: &[(NaiveDate, impl Sized)],
+) -> Option<NaiveDate> {
+    if !plot_ui.response().clicked() {
+        return None;
+    }
+    plot_ui
+        .pointer_coordinate()
+        .and_then(|pointer| day_at(first, pointer.x))
+        .filter(|day| days.binary_search_by_key(day, |(day, _)| *day).is_ok())
+}
+
+/// Charts `counts` (entries per day, in date order) as one bar per day. Returns the day whose bar
+/// was clicked.
+pub fn entries_per_day_ui(
+    ui: &mut egui::Ui,
+    counts: &[(NaiveDate, usize)],
+    settings: &DisplaySettings,
+) -> Option<NaiveDate> {
+    let &(first, _) = counts.first()?;
+    let date_format = settings.date_format;
+    let bars = counts
+        .iter()
+        .map(|&(day, count)| {
+            egui_plot::Bar::new((day - first).num_days() as f64, count as f64).width(1.0)
+        })
+        .collect::<Vec<_>>();
+
+    let mut clicked = None;
+    daily_plot("entries_per_day_plot", "Entries", first, settings).show(ui, |plot_ui| {
+        clicked = clicked_day(plot_ui, first, counts);
+        plot_ui.bar_chart(
+            egui_plot::BarChart::new("Entries per day", bars)
+                .color(settings.bar_color32())
+                .element_formatter(Box::new(move |bar, _chart| {
+                    format!(
+                        "{}\n{} entries",
+                        date_label(first, date_format, bar.argument),
+                        bar.value
+                    )
+                })),
+        );
+    });
+    clicked
+}
+
+/// Each bar's value as text on the bar's end, for [`DisplaySettings::label_daily_totals`]. Bars
+/// below zero are labelled below their end rather than above so the label never sits on them.
+pub fn total_labels(bars: &[egui_plot::Bar], number_format: NumberFormat) -> Vec<egui_plot::Text> {
+    bars.iter()
+        .map(|bar| {
+            let anchor = if bar.value >= 0.0 {
+                egui::Align2::CENTER_BOTTOM
+            } else {
+                egui::Align2::CENTER_TOP
+            };
+            egui_plot::Text::new(
+                "Daily totals",
+                egui_plot::PlotPoint::new(bar.argument, bar.value),
+                number_format.energy(bar.value),
+            )
+            .anchor(anchor)
+        })
+        .collect()
+}
+
+/// Charts `net` (each day of the range's usage less what it exported, in date order) as one bar
+/// per day, with days that exported more than they used in green. Returns the day whose bar was
+/// clicked.
+pub fn daily_net_ui(
+    ui: &mut egui::Ui,
+    net: &[(NaiveDate, f64)],
+    settings: &DisplaySettings,
+) -> Option<NaiveDate> {
+    const EXPORTING: egui::Color32 = egui::Color32::from_rgb(60, 170, 90);
+
+    let Some(&(first, _)) = net.first() else {
+        ui.label("No usage in the selected range.");
+        return None;
+    };
+    let unit = settings.unit;
+    let unit_label = unit.label();
+    let number_format = settings.number_format;
+    let date_format = settings.date_format;
+    let (importing, exporting): (Vec<_>, Vec<_>) = net
+        .iter()
+        .map(|&(day, net)| {
+            egui_plot::Bar::new((day - first).num_days() as f64, unit.convert(net)).width(1.0)
+        })
+        .partition(|bar| bar.value >= 0.0);
+    // Built before the bars are moved into their charts.
+    let labels = if settings.label_daily_totals {
+        let mut labels = total_labels(&importing, number_format);
+        labels.extend(total_labels(&exporting, number_format));
+        labels
+    } else {
+        Vec::new()
+    };
+    let formatter = move |bar: &egui_plot::Bar, _chart: &egui_plot::BarChart| {
+        format!(
+            "{}\n{} {unit_label} net",
+            date_label(first, date_format, bar.argument),
+            number_format.energy(bar.value)
+        )
+    };
+
+    let mut clicked = None;
+    let y_label = format!("Net usage ({unit_label})");
+    let plot = daily_plot("daily_net_plot", &y_label, first, settings).legend(display::legend());
+    plot.show(ui, |plot_ui| {
+        clicked = clicked_day(plot_ui, first, net);
+        plot_ui.bar_chart(
+            egui_plot::BarChart::new("Net used", importing)
+                .color(settings.bar_color32())
+                .element_formatter(Box::new(formatter)),
+        );
+        plot_ui.bar_chart(
+            egui_plot::BarChart::new("Net exported", exporting)
+                .color(EXPORTING)
+                .element_formatter(Box::new(formatter)),
+        );
+        for label in labels {
+            plot_ui.text(label);
+        }
+    });
+    clicked
+}
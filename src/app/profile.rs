@@ -0,0 +1,171 @@
+//! Saving and loading the app's settings on their own, so they can be moved between machines.
+//!
+//! Only preferences go into a profile: loaded data, comparisons, and caches stay behind.
+
+use anyhow::Context;
+
+use super::csv_export::CsvFormat;
+use super::display::DisplaySettings;
+use super::rates::RateSchedule;
+use super::signatures::SignatureSettings;
+use super::trend::TrendSettings;
+use super::{DayFilter, OnOpen, TemplateApp};
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+struct Profile {
+    day_filter: DayFilter,
+    display: DisplaySettings,
+    rates: RateSchedule,
+    alternative_rates: RateSchedule,
+    projection_days: usize,
+    stale_after_days: u32,
+    retention_days: u32,
+    exclude_partial_day: bool,
+    budget: Option<f64>,
+    emissions_factor: f64,
+    on_open: OnOpen,
+    lenient_parsing: bool,
+    skip_totals_rows: bool,
+    normalize_on_load: bool,
+    snap_hour_buckets: bool,
+    csv_format: CsvFormat,
+    show_error_causes: bool,
+    autosave_seconds: u64,
+    signature_settings: SignatureSettings,
+    trend_settings: TrendSettings,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::from_app(&TemplateApp::default())
+    }
+}
+
+impl Profile {
+    fn from_app(app: &TemplateApp) -> Self {
+        Self {
+            day_filter: app.day_filter.clone(),
+            display: app.display.clone(),
+            rates: app.rates.clone(),
+            alternative_rates: app.alternative_rates.clone(),
+            projection_days: app.projection_days,
+            stale_after_days: app.stale_after_days,
+            retention_days: app.retention_days,
+            exclude_partial_day: app.exclude_partial_day,
+            budget: app.budget,
+            emissions_factor: app.emissions_factor,
+            on_open: app.on_open,
+            lenient_parsing: app.lenient_parsing,
+            skip_totals_rows: app.skip_totals_rows,
+            normalize_on_load: app.normalize_on_load,
+            snap_hour_buckets: app.snap_hour_buckets,
+            csv_format: app.csv_format,
+            show_error_causes: app.show_error_causes,
+            autosave_seconds: app.autosave_seconds,
+            signature_settings: app.signature_settings,
+            trend_settings: app.trend_settings,
+        }
+    }
+
+    fn apply(self, app: &mut TemplateApp) {
+        app.day_filter = self.day_filter;
+        app.display = self.display;
+        app.rates = self.rates;
+        app.alternative_rates = self.alternative_rates;
+        app.projection_days = self.projection_days;
+        app.stale_after_days = self.stale_after_days;
+        app.retention_days = self.retention_days;
+        app.exclude_partial_day = self.exclude_partial_day;
+        app.budget = self.budget;
+        app.emissions_factor = self.emissions_factor;
+        app.on_open = self.on_open;
+        app.lenient_parsing = self.lenient_parsing;
+        app.skip_totals_rows = self.skip_totals_rows;
+        app.normalize_on_load = self.normalize_on_load;
+        app.csv_format = self.csv_format;
+        app.show_error_causes = self.show_error_causes;
+        app.autosave_seconds = self.autosave_seconds;
+        app.signature_settings = self.signature_settings;
+        app.trend_settings = self.trend_settings;
+        // As when the setting is toggled, data already loaded is re-bucketed to match.
+        app.set_snap_hour_buckets(self.snap_hour_buckets);
+    }
+}
+
+/// Asks where to save, then writes the settings there as JSON.
+pub fn export_settings(app: &TemplateApp) -> anyhow::Result<()> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("Settings", &["json"])
+        .set_file_name("plot-electricity-settings.json")
+        .save_file()
+    else {
+        return Ok(());
+    };
+    let json = serde_json::to_string_pretty(&Profile::from_app(app))?;
+    std::fs::write(&path, json).with_context(|| format!("Could not write {}", path.display()))
+}
+
+/// Asks for a settings file and replaces the current settings with it.
+pub fn import_settings(app: &mut TemplateApp) -> anyhow::Result<()> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("Settings", &["json"])
+        .pick_file()
+    else {
+        return Ok(());
+    };
+    let json = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let profile: Profile = serde_json::from_str(&json).context("Not a valid settings file")?;
+    profile.apply(app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::trend::Statistic;
+    use crate::app::UsageData;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let mut app = TemplateApp::default();
+        app.rates.cycle_start_day = 15;
+        app.rates.tiers[0].price = 0.25;
+        app.retention_days = 30;
+        app.budget = Some(80.0);
+        app.snap_hour_buckets = true;
+        app.trend_settings = TrendSettings {
+            bucket_hours: 6,
+            statistic: Statistic::Max,
+        };
+        let json = serde_json::to_string(&Profile::from_app(&app)).unwrap();
+
+        let mut other = TemplateApp::default();
+        serde_json::from_str::<Profile>(&json)
+            .unwrap()
+            .apply(&mut other);
+        assert!(other.rates == app.rates);
+        assert_eq!(other.retention_days, 30);
+        assert_eq!(other.budget, Some(80.0));
+        assert!(other.snap_hour_buckets);
+        assert!(other.trend_settings == app.trend_settings);
+    }
+
+    #[test]
+    fn applying_a_profile_re_snaps_loaded_data() {
+        let mut app = TemplateApp {
+            data: Some(UsageData::from_rows(
+                "Electric usage,1/15/2024,8:59 AM,9:58 AM,0.42,kWh,\n",
+            )),
+            ..TemplateApp::default()
+        };
+        let profile = Profile {
+            snap_hour_buckets: true,
+            ..Profile::default()
+        };
+        profile.apply(&mut app);
+        let entry = &app.data.as_ref().unwrap().entries[0];
+        assert_eq!(entry.hour_bucket, Some(9));
+    }
+}
@@ -0,0 +1,129 @@
+//! The range of days that totals, averages, and cost are worked out over.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+use super::rates::RateSchedule;
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    /// Inclusive.
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    /// Every day of the month containing `date`, however many days that month has.
+    pub fn month(date: NaiveDate) -> Self {
+        let start = date.with_day(1).unwrap();
+        Self {
+            start,
+            end: start + Months::new(1) - Days::new(1),
+        }
+    }
+
+    /// The entries of `daily_totals` within the range.
+    pub fn days(self, daily_totals: &BTreeMap<NaiveDate, f64>) -> BTreeMap<NaiveDate, f64> {
+        // `BTreeMap::range` panics on a backwards range, which the pickers can produce.
+        if self.start > self.end {
+            return BTreeMap::new();
+        }
+        daily_totals
+            .range(self.start..=self.end)
+            .map(|(&day, &total)| (day, total))
+            .collect()
+    }
+}
+
+/// Ranges that can be picked in one click, relative to the latest day with data.
+#[derive(Clone, Copy)]
+enum QuickRange {
+    ThisCycle,
+    LastCycle,
+    ThisMonth,
+    LastWeek,
+}
+
+impl QuickRange {
+    const ALL: [Self; 4] = [
+        Self::ThisCycle,
+        Self::LastCycle,
+        Self::ThisMonth,
+        Self::LastWeek,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::ThisCycle => "This cycle",
+            Self::LastCycle => "Last cycle",
+            Self::ThisMonth => "This month",
+            Self::LastWeek => "Last 7 days",
+        }
+    }
+
+    fn range(self, latest: NaiveDate, rates: &RateSchedule) -> DateRange {
+        let cycle_start = rates.cycle_start(latest);
+        match self {
+            Self::ThisCycle => DateRange {
+                start: cycle_start,
+                end: latest,
+            },
+            Self::LastCycle => {
+                let end = cycle_start - Days::new(1);
+                DateRange {
+                    start: rates.cycle_start(end),
+                    end,
+                }
+            }
+            Self::ThisMonth => DateRange {
+                start: latest.with_day(1).unwrap(),
+                end: latest,
+            },
+            Self::LastWeek => DateRange {
+                start: latest - Days::new(6),
+                end: latest,
+            },
+        }
+    }
+}
+
+/// Two date pickers plus the quick-select buttons, one of which covers the whole month of
+/// `selected`. `daily_totals` decides which quick ranges have any data and so are worth offering.
+pub fn range_ui(
+    ui: &mut egui::Ui,
+    range: &mut DateRange,
+    daily_totals: &BTreeMap<NaiveDate, f64>,
+    rates: &RateSchedule,
+    selected: NaiveDate,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Range");
+        ui.add(egui_extras::DatePickerButton::new(&mut range.start).id_salt("range_start"));
+        ui.label("to");
+        ui.add(egui_extras::DatePickerButton::new(&mut range.end).id_salt("range_end"));
+        if ui
+            .button(format!("All of {}", selected.format("%B")))
+            .clicked()
+        {
+            *range = DateRange::month(selected);
+        }
+
+        let Some(&latest) = daily_totals.keys().next_back() else {
+            return;
+        };
+        for quick in QuickRange::ALL {
+            let quick_range = quick.range(latest, rates);
+            let has_data = daily_totals
+                .range(quick_range.start..=quick_range.end)
+                .next()
+                .is_some();
+            if ui
+                .add_enabled(has_data, egui::Button::new(quick.label()))
+                .clicked()
+            {
+                *range = quick_range;
+            }
+        }
+    });
+}
@@ -0,0 +1,163 @@
+//! Drawing a day's bar chart as a PNG, for reports that embed their charts as images.
+//!
+//! The image has no text in it: whatever shows it labels the chart, so only the bars, the axes,
+//! and their ticks are drawn, each one a filled rectangle.
+
+use anyhow::Context;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 300;
+const MARGIN_LEFT: f64 = 20.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_BOTTOM: f64 = 20.0;
+/// Bars are placed on a whole day, in hours since midnight, as in [`super::svg`].
+const X_RANGE: (f64, f64) = (0.0, 24.0);
+const X_TICK_HOURS: f64 = 3.0;
+const Y_TICKS: usize = 5;
+
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const AXIS: [u8; 3] = [0, 0, 0];
+const GRID: [u8; 3] = [225, 225, 225];
+
+/// Unmultiplied RGBA pixels, a row at a time from the top.
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        let pixel = [BACKGROUND[0], BACKGROUND[1], BACKGROUND[2], 255];
+        Self {
+            pixels: pixel.repeat((WIDTH * HEIGHT) as usize),
+        }
+    }
+
+    /// Fills the pixels whose centers fall between `left` and `right` and `top` and `bottom`,
+    /// blending `color` over what's there by `alpha`. Anything off the canvas is left out.
+    fn fill(
+        &mut self,
+        (left, right): (f64, f64),
+        (top, bottom): (f64, f64),
+        color: [u8; 3],
+        alpha: u8,
+    ) {
+        let clamp = |value: f64, max: u32| (value.round().max(0.0) as u32).min(max);
+        let alpha = alpha as u32;
+        for y in clamp(top, HEIGHT)..clamp(bottom, HEIGHT) {
+            for x in clamp(left, WIDTH)..clamp(right, WIDTH) {
+                let i = ((y * WIDTH + x) * 4) as usize;
+                for (channel, &value) in self.pixels[i..i + 3].iter_mut().zip(&color) {
+                    *channel =
+                        ((value as u32 * alpha + *channel as u32 * (255 - alpha)) / 255) as u8;
+                }
+            }
+        }
+    }
+
+    fn encode(self) -> anyhow::Result<Vec<u8>> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .context("Could not encode the chart")?;
+        writer
+            .write_image_data(&self.pixels)
+            .context("Could not encode the chart")?;
+        writer.finish().context("Could not encode the chart")?;
+        Ok(png)
+    }
+}
+
+/// Draws `bars` (in plot coordinates, as drawn on screen) as a PNG, scaled so the tallest bar
+/// reaches the top of the chart and with light lines at fifths of that height.
+pub fn bar_chart_png(bars: &[egui_plot::Bar], color: egui::Color32) -> anyhow::Result<Vec<u8>> {
+    let bottom = |bar: &egui_plot::Bar| bar.base_offset.unwrap_or(0.0);
+    let y_min = bars.iter().map(bottom).fold(0.0, f64::min);
+    let mut y_max = bars
+        .iter()
+        .map(|bar| bottom(bar) + bar.value)
+        .fold(y_min, f64::max);
+    if y_max <= y_min {
+        y_max = y_min + 1.0;
+    }
+
+    let plot_width = WIDTH as f64 - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT as f64 - MARGIN_TOP - MARGIN_BOTTOM;
+    let to_x = |x: f64| MARGIN_LEFT + (x - X_RANGE.0) / (X_RANGE.1 - X_RANGE.0) * plot_width;
+    let to_y = |y: f64| MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+    let right_edge = WIDTH as f64 - MARGIN_RIGHT;
+    let mut canvas = Canvas::new();
+
+    for i in 1..=Y_TICKS {
+        let y = to_y(y_min + (y_max - y_min) * i as f64 / Y_TICKS as f64);
+        canvas.fill((MARGIN_LEFT, right_edge), (y, y + 1.0), GRID, 255);
+    }
+
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    for bar in bars {
+        let half_width = bar.bar_width / 2.0;
+        let top = to_y(bottom(bar) + bar.value);
+        let base = to_y(bottom(bar));
+        canvas.fill(
+            (
+                to_x(bar.argument - half_width),
+                to_x(bar.argument + half_width),
+            ),
+            (top.min(base), top.max(base)),
+            [r, g, b],
+            a,
+        );
+    }
+
+    let axis_y = to_y(y_min);
+    canvas.fill((MARGIN_LEFT, right_edge), (axis_y, axis_y + 1.0), AXIS, 255);
+    canvas.fill(
+        (MARGIN_LEFT - 1.0, MARGIN_LEFT),
+        (MARGIN_TOP, axis_y + 1.0),
+        AXIS,
+        255,
+    );
+    let mut hour = X_RANGE.0;
+    while hour <= X_RANGE.1 {
+        let x = to_x(hour);
+        canvas.fill((x - 0.5, x + 0.5), (axis_y, axis_y + 6.0), AXIS, 255);
+        hour += X_TICK_HOURS;
+    }
+
+    canvas.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(image: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * WIDTH + x) * 4) as usize;
+        image[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn bars_are_drawn_to_scale() {
+        let bars = [
+            egui_plot::Bar::new(0.5, 2.0).width(1.0),
+            egui_plot::Bar::new(12.5, 1.0).width(1.0),
+        ];
+        let color = egui::Color32::from_rgb(10, 20, 30);
+        let png = bar_chart_png(&bars, color).unwrap();
+
+        let decoder = png::Decoder::new(png.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut image = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut image).unwrap();
+        assert_eq!((info.width, info.height), (WIDTH, HEIGHT));
+
+        // The first bar fills the chart's height; the second reaches halfway, at y = 145.
+        assert_eq!(pixel(&image, 30, 15), [10, 20, 30, 255]);
+        assert_eq!(pixel(&image, 410, 150), [10, 20, 30, 255]);
+        assert_eq!(pixel(&image, 410, 140), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 200, 150), [255, 255, 255, 255]);
+    }
+}
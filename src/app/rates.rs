@@ -0,0 +1,474 @@
+//! The rate schedule used to turn energy into an estimated cost.
+//!
+//! Utilities commonly price energy in tiers: the first so many kWh of a billing cycle at one
+//! price, the next block at a higher price, and so on. A single unlimited tier is a flat rate.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use super::display::{Currency, DateFormat, NumberFormat};
+use super::UsageData;
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RateTier {
+    pub name: String,
+    /// kWh per billing cycle billed at this tier's price, or `None` for everything remaining.
+    pub allowance: Option<f64>,
+    /// Price per kWh.
+    pub price: f64,
+}
+
+#[derive(Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct RateSchedule {
+    /// Day of the month each billing cycle starts on. Kept to 1..=28 so every month has it.
+    pub cycle_start_day: u32,
+    pub tiers: Vec<RateTier>,
+}
+
+impl Default for RateSchedule {
+    fn default() -> Self {
+        Self {
+            cycle_start_day: 1,
+            tiers: vec![RateTier {
+                name: "Standard".to_owned(),
+                allowance: None,
+                price: 0.15,
+            }],
+        }
+    }
+}
+
+impl RateSchedule {
+    /// How much of `kilowatt_hours` used within a single billing cycle falls into each tier, in
+    /// order, stopping at the tier where it runs out. A cycle that exported more than it used
+    /// puts all of its (negative) usage in the first tier. Whatever is left past the last tier's
+    /// allowance is billed in the last tier, rather than for free.
+    fn split_into_tiers(&self, kilowatt_hours: f64) -> impl Iterator<Item = (&RateTier, f64)> {
+        let last = self.tiers.len().saturating_sub(1);
+        let mut remaining = Some(kilowatt_hours);
+        self.tiers.iter().enumerate().map_while(move |(i, tier)| {
+            let left = remaining?;
+            let in_tier = match tier.allowance {
+                Some(allowance) if i < last => left.min(allowance),
+                _ => left,
+            };
+            remaining = Some(left - in_tier).filter(|left| *left > 0.0);
+            Some((tier, in_tier))
+        })
+    }
+
+    /// Cost of using `kilowatt_hours` within a single billing cycle. A cycle that exported more
+    /// than it used comes out negative, credited at the first tier's price.
+    pub fn cycle_cost(&self, kilowatt_hours: f64) -> f64 {
+        self.split_into_tiers(kilowatt_hours)
+            .map(|(tier, in_tier)| in_tier * tier.price)
+            .sum()
+    }
+
+    /// [`Self::cycle_cost`] line by line, one row per tier the usage reaches.
+    pub fn breakdown(&self, kilowatt_hours: f64) -> Vec<TierUsage> {
+        self.split_into_tiers(kilowatt_hours)
+            .map(|(tier, in_tier)| TierUsage {
+                name: tier.name.clone(),
+                kilowatt_hours: in_tier,
+                price: tier.price,
+                cost: in_tier * tier.price,
+            })
+            .collect()
+    }
+
+    /// Cost of `kilowatt_hours` used after `used_before` kWh earlier in the same cycle, so it is
+    /// billed at whichever tiers it falls into.
+    pub fn marginal_cost(&self, used_before: f64, kilowatt_hours: f64) -> f64 {
+        self.cycle_cost(used_before + kilowatt_hours) - self.cycle_cost(used_before)
+    }
+
+    /// First day of the billing cycle containing `date`.
+    pub fn cycle_start(&self, date: NaiveDate) -> NaiveDate {
+        let start = date.with_day(self.cycle_start_day.clamp(1, 28)).unwrap();
+        if start <= date {
+            start
+        } else {
+            start - Months::new(1)
+        }
+    }
+
+    /// Cost of the given days, billing each cycle's share of them separately.
+    pub fn days_cost(&self, daily_totals: &BTreeMap<NaiveDate, f64>) -> f64 {
+        let mut cycles = BTreeMap::new();
+        for (&day, &total) in daily_totals {
+            *cycles.entry(self.cycle_start(day)).or_insert(0.0) += total;
+        }
+        cycles.values().map(|&total| self.cycle_cost(total)).sum()
+    }
+
+    /// Total usage in each billing cycle, keyed by the cycle's first day.
+    pub fn cycle_totals(&self, data: &UsageData) -> BTreeMap<NaiveDate, f64> {
+        let mut totals = BTreeMap::new();
+        for entry in data.included() {
+            *totals.entry(self.cycle_start(entry.day())).or_insert(0.0) += entry.kilowatt_hours;
+        }
+        totals
+    }
+}
+
+/// One line of a billing cycle's cost: the usage billed at one tier.
+pub struct TierUsage {
+    pub name: String,
+    pub kilowatt_hours: f64,
+    pub price: f64,
+    pub cost: f64,
+}
+
+/// The cost of every entry, worked out once rather than every frame the costs are drawn.
+pub struct EntryCosts {
+    /// The schedule the costs were priced with; any edit to the schedule makes them stale.
+    pub schedule: RateSchedule,
+    /// Parallel to the entries: each entry billed at the tiers it falls into, given everything
+    /// used before it in its billing cycle. Excluded entries cost nothing.
+    pub costs: Vec<f64>,
+}
+
+impl EntryCosts {
+    pub fn new(data: &UsageData, schedule: &RateSchedule) -> Self {
+        let mut used = BTreeMap::new();
+        let costs = data
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.excluded {
+                    return 0.0;
+                }
+                let used = used.entry(schedule.cycle_start(entry.day())).or_insert(0.0);
+                let cost = schedule.marginal_cost(*used, entry.kilowatt_hours);
+                *used += entry.kilowatt_hours;
+                cost
+            })
+            .collect();
+        Self {
+            schedule: schedule.clone(),
+            costs,
+        }
+    }
+}
+
+/// An estimate of upcoming cost, extrapolated from recent usage.
+pub struct Projection {
+    /// How many days of data the estimate is based on, which may be fewer than asked for.
+    pub days: usize,
+    pub cost: f64,
+}
+
+/// Estimates the cost of a `horizon`-day billing cycle by assuming every day in it uses the
+/// average of the last `window` days in `daily_totals`. Returns `None` with no data to go on.
+pub fn project_cost(
+    daily_totals: &BTreeMap<NaiveDate, f64>,
+    window: usize,
+    horizon: usize,
+    schedule: &RateSchedule,
+) -> Option<Projection> {
+    let recent = daily_totals.values().rev().take(window).collect::<Vec<_>>();
+    if recent.is_empty() {
+        return None;
+    }
+    let daily_average = recent.iter().copied().sum::<f64>() / recent.len() as f64;
+    Some(Projection {
+        days: recent.len(),
+        cost: schedule.cycle_cost(daily_average * horizon as f64),
+    })
+}
+
+pub fn rate_schedule_ui(ui: &mut egui::Ui, schedule: &mut RateSchedule, currency: Currency) {
+    ui.horizontal(|ui| {
+        ui.label("Billing cycle starts on day");
+        ui.add(egui::DragValue::new(&mut schedule.cycle_start_day).range(1..=28));
+    });
+
+    let tier_count = schedule.tiers.len();
+    let mut removed = None;
+    for (i, tier) in schedule.tiers.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(&mut tier.name).desired_width(80.0));
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(i);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label(currency.symbol());
+                ui.add(
+                    egui::DragValue::new(&mut tier.price)
+                        .speed(0.001)
+                        .range(0.0..=f64::INFINITY),
+                );
+                ui.label("/kWh");
+                // Only the last tier may be unlimited; earlier ones need an allowance to end.
+                if i + 1 == tier_count {
+                    let mut unlimited = tier.allowance.is_none();
+                    if ui.checkbox(&mut unlimited, "Unlimited").changed() {
+                        tier.allowance = if unlimited { None } else { Some(100.0) };
+                    }
+                } else if tier.allowance.is_none() {
+                    tier.allowance = Some(100.0);
+                }
+                if let Some(allowance) = &mut tier.allowance {
+                    ui.label("for");
+                    ui.add(egui::DragValue::new(allowance).range(0.0..=f64::INFINITY));
+                    ui.label("kWh");
+                }
+            });
+        });
+    }
+    if let Some(i) = removed {
+        schedule.tiers.remove(i);
+    }
+    if ui.button("Add tier").clicked() {
+        schedule.tiers.push(RateTier {
+            name: format!("Tier {}", schedule.tiers.len() + 1),
+            allowance: None,
+            price: schedule.tiers.last().map_or(0.15, |tier| tier.price),
+        });
+    }
+}
+
+/// Shows how the cost of one billing cycle in `cycle_totals` adds up tier by tier, defaulting to
+/// the latest cycle. Returns the cycle's first day and its rows if they should be exported.
+pub fn breakdown_ui(
+    ui: &mut egui::Ui,
+    schedule: &RateSchedule,
+    cycle_totals: &BTreeMap<NaiveDate, f64>,
+    cycle: &mut Option<NaiveDate>,
+    date_format: DateFormat,
+    number_format: NumberFormat,
+) -> Option<(NaiveDate, Vec<TierUsage>)> {
+    let (&start, &total) = cycle
+        .and_then(|start| cycle_totals.get_key_value(&start))
+        .or_else(|| cycle_totals.last_key_value())?;
+    egui::ComboBox::from_id_salt("breakdown_cycle")
+        .selected_text(format!("Cycle from {}", date_format.format(start)))
+        .show_ui(ui, |ui| {
+            for &other in cycle_totals.keys().rev() {
+                ui.selectable_value(cycle, Some(other), date_format.format(other));
+            }
+        });
+
+    let rows = schedule.breakdown(total);
+    egui::Grid::new("breakdown_grid")
+        .striped(true)
+        .num_columns(4)
+        .show(ui, |ui| {
+            ui.strong("Tier");
+            ui.strong("kWh");
+            ui.strong("Rate");
+            ui.strong("Subtotal");
+            ui.end_row();
+            for row in &rows {
+                ui.label(&row.name);
+                ui.label(number_format.energy(row.kilowatt_hours));
+                ui.label(format!("{}/kWh", number_format.cost(row.price)));
+                ui.label(number_format.cost(row.cost));
+                ui.end_row();
+            }
+            ui.strong("Total");
+            ui.strong(number_format.energy(total));
+            ui.label("");
+            ui.strong(number_format.cost(rows.iter().map(|row| row.cost).sum()));
+            ui.end_row();
+        });
+    ui.button("Export CSV").clicked().then_some((start, rows))
+}
+
+/// The same billing cycle as [`breakdown_ui`] priced under `current` and under `alternative`,
+/// with what switching would save. Both are billed on the current plan's cycles, so the
+/// alternative's cycle start day doesn't come into it.
+pub fn plan_comparison_ui(
+    ui: &mut egui::Ui,
+    current: &RateSchedule,
+    alternative: &RateSchedule,
+    cycle_totals: &BTreeMap<NaiveDate, f64>,
+    cycle: Option<NaiveDate>,
+    date_format: DateFormat,
+    number_format: NumberFormat,
+) {
+    let Some((&start, &total)) = cycle
+        .and_then(|start| cycle_totals.get_key_value(&start))
+        .or_else(|| cycle_totals.last_key_value())
+    else {
+        return;
+    };
+    let current_cost = current.cycle_cost(total);
+    let alternative_cost = alternative.cycle_cost(total);
+    let savings = current_cost - alternative_cost;
+    ui.label(format!(
+        "Cycle from {}: {} kWh",
+        date_format.format(start),
+        number_format.energy(total)
+    ));
+    egui::Grid::new("plan_comparison_grid")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Current plan");
+            ui.label(number_format.cost(current_cost));
+            ui.end_row();
+            ui.label("Alternative");
+            ui.label(number_format.cost(alternative_cost));
+            ui.end_row();
+            ui.strong(if savings >= 0.0 {
+                "Saves"
+            } else {
+                "Costs more"
+            });
+            let percent = if current_cost.abs() > f64::EPSILON {
+                format!(" ({:.1}%)", savings.abs() / current_cost.abs() * 100.0)
+            } else {
+                String::new()
+            };
+            ui.strong(format!("{}{percent}", number_format.cost(savings.abs())));
+            ui.end_row();
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiered() -> RateSchedule {
+        RateSchedule {
+            cycle_start_day: 1,
+            tiers: vec![
+                RateTier {
+                    name: "Baseline".to_owned(),
+                    allowance: Some(100.0),
+                    price: 0.10,
+                },
+                RateTier {
+                    name: "Peak".to_owned(),
+                    allowance: Some(50.0),
+                    price: 0.30,
+                },
+            ],
+        }
+    }
+
+    fn split(schedule: &RateSchedule, kilowatt_hours: f64) -> Vec<(&str, f64)> {
+        schedule
+            .split_into_tiers(kilowatt_hours)
+            .map(|(tier, in_tier)| (tier.name.as_str(), in_tier))
+            .collect()
+    }
+
+    #[test]
+    fn usage_fills_tiers_in_order() {
+        let schedule = tiered();
+        assert_eq!(split(&schedule, 40.0), [("Baseline", 40.0)]);
+        assert_eq!(
+            split(&schedule, 120.0),
+            [("Baseline", 100.0), ("Peak", 20.0)]
+        );
+    }
+
+    #[test]
+    fn usage_past_a_capped_last_tier_is_billed_there() {
+        let schedule = tiered();
+        assert_eq!(
+            split(&schedule, 200.0),
+            [("Baseline", 100.0), ("Peak", 100.0)]
+        );
+        assert!((schedule.cycle_cost(200.0) - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn net_export_is_credited_at_the_first_tier() {
+        let schedule = tiered();
+        assert_eq!(split(&schedule, -30.0), [("Baseline", -30.0)]);
+        assert!((schedule.cycle_cost(-30.0) + 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn breakdown_adds_up_to_the_cycle_cost() {
+        let schedule = tiered();
+        let rows = schedule.breakdown(130.0);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].name, "Peak");
+        assert!((rows[1].kilowatt_hours - 30.0).abs() < 1e-9);
+        let total = rows.iter().map(|row| row.cost).sum::<f64>();
+        assert!((total - schedule.cycle_cost(130.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn marginal_cost_crosses_into_the_next_tier() {
+        let schedule = tiered();
+        // 10 kWh at the baseline price, then 10 at peak.
+        assert!((schedule.marginal_cost(90.0, 20.0) - 4.0).abs() < 1e-9);
+    }
+
+    fn daily_totals(totals: &[f64]) -> BTreeMap<NaiveDate, f64> {
+        let first = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        totals
+            .iter()
+            .zip(first.iter_days())
+            .map(|(&total, day)| (day, total))
+            .collect()
+    }
+
+    #[test]
+    fn projection_averages_the_most_recent_days() {
+        // Only the last two days count: an average of 4 kWh a day for 30 days.
+        let totals = daily_totals(&[100.0, 3.0, 5.0]);
+        let projection = project_cost(&totals, 2, 30, &tiered()).unwrap();
+        assert_eq!(projection.days, 2);
+        assert!((projection.cost - (100.0 * 0.10 + 20.0 * 0.30)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn projection_with_fewer_days_than_the_window() {
+        let projection = project_cost(&daily_totals(&[2.0]), 14, 30, &tiered()).unwrap();
+        assert_eq!(projection.days, 1);
+        assert!((projection.cost - 6.0).abs() < 1e-9);
+        assert!(project_cost(&BTreeMap::new(), 14, 30, &tiered()).is_none());
+    }
+
+    #[test]
+    fn entry_costs_match_pricing_each_cycle_afresh() {
+        let mut data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,8:00 AM,9:00 AM,90.0,kWh,
+Electric usage,1/20/2024,8:00 AM,9:00 AM,20.0,kWh,
+Electric usage,1/25/2024,8:00 AM,9:00 AM,5.0,kWh,
+Electric usage,2/1/2024,8:00 AM,9:00 AM,20.0,kWh,
+",
+        );
+        let schedule = tiered();
+        let costs = EntryCosts::new(&data, &schedule).costs;
+        assert_eq!(costs.len(), 4);
+        let january = costs[..3].iter().sum::<f64>();
+        assert!((january - schedule.cycle_cost(115.0)).abs() < 1e-9);
+        // The second entry crosses into the peak tier; February starts over.
+        assert!((costs[1] - 4.0).abs() < 1e-9);
+        assert!((costs[3] - schedule.cycle_cost(20.0)).abs() < 1e-9);
+
+        data.entries[1].excluded = true;
+        let costs = EntryCosts::new(&data, &schedule).costs;
+        assert_eq!(costs[1], 0.0);
+        assert!((costs[2] - schedule.marginal_cost(90.0, 5.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exporting_more_than_was_used_is_a_credit() {
+        let data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,12:00 PM,1:00 PM,1.0,kWh,
+Electric usage,1/15/2024,1:00 PM,2:00 PM,-3.0,kWh,
+",
+        );
+        let schedule = tiered();
+        let total = EntryCosts::new(&data, &schedule).costs.iter().sum::<f64>();
+        assert!((total + 0.2).abs() < 1e-9);
+        assert_eq!(NumberFormat::default().cost(total), "-$0.20");
+
+        let projection = project_cost(&daily_totals(&[-2.0]), 14, 30, &schedule).unwrap();
+        assert!((projection.cost + 6.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,101 @@
+//! The raw text of a file that failed to parse: a look around the bad line, and retrying it as
+//! a slightly different format.
+
+/// How many lines either side of the bad one to show.
+const CONTEXT_LINES: usize = 3;
+
+/// What a file was being loaded as, so a retry that parses puts it back in the same place.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Target {
+    /// The file being looked at.
+    Main,
+    /// One of the files compared against it.
+    Comparison,
+}
+
+pub struct FailedInput {
+    /// Where the text came from, to open it as once a retry parses.
+    pub path: Option<std::path::PathBuf>,
+    pub target: Target,
+    pub text: String,
+    /// The 1-based line the error points at, if it points at one.
+    pub line: Option<usize>,
+}
+
+/// A common way an export differs from the portal format, which the text can be rewritten from.
+#[derive(Clone, Copy)]
+pub enum Retry {
+    /// Dates written day first, as D/M/Y.
+    DayFirst,
+    /// Semicolons between columns instead of commas.
+    Semicolons,
+}
+
+impl Retry {
+    pub const ALL: [Self; 2] = [Self::DayFirst, Self::Semicolons];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::DayFirst => "Retry as D/M/Y",
+            Self::Semicolons => "Retry with semicolons",
+        }
+    }
+
+    /// Rewrites `text` into the portal format this retry assumes it was a variant of.
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Self::DayFirst => text
+                .split('\n')
+                .map(|line| {
+                    let mut fields = line.splitn(3, ',');
+                    let (Some(kind), Some(date), rest) =
+                        (fields.next(), fields.next(), fields.next())
+                    else {
+                        return line.to_owned();
+                    };
+                    let mut parts = date.split('/');
+                    let (Some(day), Some(month), Some(year), None) =
+                        (parts.next(), parts.next(), parts.next(), parts.next())
+                    else {
+                        return line.to_owned();
+                    };
+                    match rest {
+                        Some(rest) => format!("{kind},{month}/{day}/{year},{rest}"),
+                        None => format!("{kind},{month}/{day}/{year}"),
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Semicolons => text.replace(';', ","),
+        }
+    }
+}
+
+pub fn raw_input_window(ctx: &egui::Context, input: &FailedInput, open: &mut bool) {
+    let Some(bad_line) = input.line else {
+        return;
+    };
+    egui::Window::new("Raw file")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            let first = bad_line.saturating_sub(CONTEXT_LINES).max(1);
+            for (i, line) in input
+                .text
+                .split('\n')
+                .enumerate()
+                .skip(first - 1)
+                .take(bad_line + CONTEXT_LINES + 1 - first)
+            {
+                let text = egui::RichText::new(format!("{:>6} | {line}", i + 1)).monospace();
+                if i + 1 == bad_line {
+                    ui.label(
+                        text.color(ui.visuals().strong_text_color())
+                            .background_color(ui.visuals().error_fg_color.gamma_multiply(0.3)),
+                    );
+                } else {
+                    ui.label(text);
+                }
+            }
+        });
+}
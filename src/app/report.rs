@@ -0,0 +1,219 @@
+//! One HTML file charting every day in a range, for sharing a month's usage in a single file.
+//!
+//! The charts are PNGs embedded in the page as data URIs, so the file opens anywhere without any
+//! images beside it.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use base64::Engine as _;
+use chrono::NaiveDate;
+
+use super::display::DisplaySettings;
+use super::range::DateRange;
+use super::raster;
+use super::svg::escape;
+use super::{DayFilter, UsageData, UsageEntry};
+
+/// How many of the range's highest-usage days the report lists up front.
+const TOP_DAYS: usize = 5;
+
+/// One heading in the report, with a line about it and its chart.
+pub struct Section {
+    pub heading: String,
+    pub summary: String,
+    /// A PNG image, embedded as it is.
+    pub chart: Vec<u8>,
+}
+
+/// Lays out `sections` under `title`, the `overview` lines, and a table of `top_days` (each a
+/// day and its usage, already formatted) as a standalone HTML document.
+pub fn report_html(
+    title: &str,
+    overview: &[String],
+    top_days: &[(String, String)],
+    sections: &[Section],
+) -> String {
+    let mut html = String::new();
+    // Writing to a `String` can't fail, so the results are ignored throughout.
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body {{ font-family: sans-serif; max-width: 840px; margin: auto; }} \
+         td, th {{ padding: 2px 12px; text-align: left; }}</style>\n\
+         </head>\n<body>\n<h1>{}</h1>",
+        escape(title),
+        escape(title)
+    );
+    if !overview.is_empty() {
+        html.push_str("<ul>\n");
+        for line in overview {
+            let _ = writeln!(html, "<li>{}</li>", escape(line));
+        }
+        html.push_str("</ul>\n");
+    }
+    if !top_days.is_empty() {
+        html.push_str("<table>\n<tr><th>Top day</th><th>Usage</th></tr>\n");
+        for (day, usage) in top_days {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(day),
+                escape(usage)
+            );
+        }
+        html.push_str("</table>\n");
+    }
+    for section in sections {
+        let _ = writeln!(
+            html,
+            "<section>\n<h2>{}</h2>\n<p>{}</p>\n\
+             <img src=\"data:image/png;base64,{}\" alt=\"{}\" style=\"width: 100%\">\n</section>",
+            escape(&section.heading),
+            escape(&section.summary),
+            base64::engine::general_purpose::STANDARD.encode(&section.chart),
+            escape(&section.heading)
+        );
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// A report on the days in `range` that pass `filter`: the range's total and top days, then
+/// each day's hourly chart.
+pub fn range_report(
+    data: &UsageData,
+    range: DateRange,
+    filter: &DayFilter,
+    settings: &DisplaySettings,
+) -> anyhow::Result<String> {
+    let unit = settings.unit;
+    let number_format = settings.number_format;
+    let date_format = settings.date_format;
+    let daily_totals = range.days(&data.daily_totals(filter));
+    let mut days: BTreeMap<NaiveDate, Vec<&UsageEntry>> = BTreeMap::new();
+    for entry in data.included() {
+        if daily_totals.contains_key(&entry.day()) {
+            days.entry(entry.day()).or_default().push(entry);
+        }
+    }
+
+    let energy = |kilowatt_hours: f64| {
+        format!(
+            "{} {}",
+            number_format.energy(unit.convert(kilowatt_hours)),
+            unit.label()
+        )
+    };
+    let overview = vec![format!(
+        "Total: {} over {} days",
+        energy(daily_totals.values().sum()),
+        daily_totals.len()
+    )];
+    let mut top_days = daily_totals.iter().collect::<Vec<_>>();
+    top_days.sort_by(|a, b| b.1.total_cmp(a.1));
+    let top_days = top_days
+        .into_iter()
+        .take(TOP_DAYS)
+        .map(|(day, &total)| (date_format.format(*day), energy(total)))
+        .collect::<Vec<_>>();
+
+    let sections = days
+        .iter()
+        .map(|(day, entries)| {
+            let bars = entries
+                .iter()
+                .map(|entry| {
+                    let width = entry.duration_hours();
+                    egui_plot::Bar::new(
+                        entry.start_hours() + width / 2.0,
+                        unit.convert(entry.kilowatt_hours),
+                    )
+                    .width(width)
+                })
+                .collect::<Vec<_>>();
+            // The image has no labels, so the summary gives its scale.
+            let tallest = entries
+                .iter()
+                .map(|entry| entry.kilowatt_hours)
+                .fold(0.0, f64::max);
+            Ok(Section {
+                heading: format!("{} {}", day.format("%A"), date_format.format(*day)),
+                summary: format!(
+                    "Total: {}. Midnight to midnight, up to {} an interval.",
+                    energy(daily_totals[day]),
+                    energy(tallest)
+                ),
+                chart: raster::bar_chart_png(&bars, settings.bar_color32())?,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let title = format!(
+        "Usage from {} to {}",
+        date_format.format(range.start),
+        date_format.format(range.end)
+    );
+    Ok(report_html(&title, &overview, &top_days, &sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_escapes_text_and_tables_the_top_days() {
+        let html = report_html(
+            "Usage at 1 <Main> & Co",
+            &["Total: 3.5 kWh over 2 days".to_owned()],
+            &[
+                ("1/16/2024".to_owned(), "2.000 kWh".to_owned()),
+                ("1/15/2024".to_owned(), "1.500 kWh".to_owned()),
+            ],
+            &[Section {
+                heading: "Monday \"the 15th\"".to_owned(),
+                summary: "Total: 1.500 kWh".to_owned(),
+                chart: vec![1, 2, 3],
+            }],
+        );
+        assert!(html.contains("<title>Usage at 1 &lt;Main&gt; &amp; Co</title>"));
+        assert!(html.contains("<h1>Usage at 1 &lt;Main&gt; &amp; Co</h1>"));
+        assert!(html.contains("<li>Total: 3.5 kWh over 2 days</li>"));
+        assert!(html.contains(
+            "<tr><th>Top day</th><th>Usage</th></tr>\n\
+             <tr><td>1/16/2024</td><td>2.000 kWh</td></tr>\n\
+             <tr><td>1/15/2024</td><td>1.500 kWh</td></tr>\n</table>"
+        ));
+        assert!(html.contains("<h2>Monday &quot;the 15th&quot;</h2>"));
+        assert!(html.contains(
+            r#"<img src="data:image/png;base64,AQID" alt="Monday &quot;the 15th&quot;""#
+        ));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn range_report_covers_each_day_in_the_range() {
+        let data = UsageData::from_rows(
+            "\
+Electric usage,1/15/2024,7:00 AM,8:00 AM,1.5,kWh,
+Electric usage,1/16/2024,7:00 AM,8:00 AM,2.0,kWh,
+Electric usage,2/1/2024,7:00 AM,8:00 AM,9.0,kWh,
+",
+        );
+        let january = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let settings = DisplaySettings::default();
+        let html = range_report(
+            &data,
+            DateRange::month(january),
+            &DayFilter::default(),
+            &settings,
+        )
+        .unwrap();
+        assert_eq!(html.matches("<section>").count(), 2);
+        assert_eq!(html.matches("data:image/png;base64,").count(), 2);
+        assert!(html.contains("<li>Total: 3.500 kWh over 2 days</li>"));
+        let top = html.find("<td>2024-01-16</td>").unwrap();
+        assert!(top < html.find("<td>2024-01-15</td>").unwrap());
+        assert!(!html.contains("9.000"));
+    }
+}
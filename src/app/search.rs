@@ -0,0 +1,140 @@
+//! Finding entries by their note or by how much they used, across the whole dataset.
+
+use chrono::NaiveDate;
+
+use super::display::{self, DateFormat, NumberFormat, TimeFormat};
+use super::{UsageData, UsageEntry};
+
+/// Most matches listed at once; beyond this the list is cut short with a count.
+const MAX_RESULTS: usize = 1000;
+
+/// Bounds on an entry's usage, both inclusive, for finding when something in particular ran.
+pub struct UsageBounds {
+    pub enabled: bool,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for UsageBounds {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min: 0.5,
+            max: 2.0,
+        }
+    }
+}
+
+/// Lists `matches`, each as its start followed by `detail`. Returns the day of one that was
+/// clicked.
+fn results_ui(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    matches: &[&UsageEntry],
+    date_format: DateFormat,
+    time_format: TimeFormat,
+    detail: impl Fn(&UsageEntry) -> String,
+) -> Option<NaiveDate> {
+    let mut clicked = None;
+    egui::ScrollArea::vertical()
+        .id_salt(id_salt)
+        .max_height(200.0)
+        .show_rows(ui, 18.0, matches.len().min(MAX_RESULTS), |ui, rows| {
+            for entry in &matches[rows] {
+                let text = format!(
+                    "{} {}  {}",
+                    date_format.format(entry.day()),
+                    time_format.format(entry.interval_start_hour, entry.interval_start_minute),
+                    detail(entry)
+                );
+                if ui.selectable_label(false, text).clicked() {
+                    clicked = Some(entry.day());
+                }
+            }
+        });
+    if matches.len() > MAX_RESULTS {
+        ui.weak(format!("Showing the first {MAX_RESULTS}"));
+    }
+    clicked
+}
+
+/// Lists entries whose note matches `query`, with notes cut to `note_length` characters.
+/// Returns the day of a result that was clicked.
+pub fn note_search_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    date_format: DateFormat,
+    time_format: TimeFormat,
+    note_length: usize,
+    query: &mut String,
+) -> Option<NaiveDate> {
+    ui.add(egui::TextEdit::singleline(query).hint_text("Search notes"));
+    if query.trim().is_empty() {
+        return None;
+    }
+
+    let matches = data
+        .entries
+        .iter()
+        .filter(|entry| entry.note_matches(query))
+        .collect::<Vec<_>>();
+    ui.label(format!("{} matches", matches.len()));
+    results_ui(
+        ui,
+        "note_search_results",
+        &matches,
+        date_format,
+        time_format,
+        |entry| display::truncate(entry.note(), note_length).into_owned(),
+    )
+}
+
+/// Lists entries whose usage is within `bounds`, with their count and total. Returns the day of a
+/// result that was clicked.
+pub fn usage_search_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    date_format: DateFormat,
+    time_format: TimeFormat,
+    number_format: NumberFormat,
+    bounds: &mut UsageBounds,
+) -> Option<NaiveDate> {
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut bounds.enabled, "Intervals using");
+        ui.add(
+            egui::DragValue::new(&mut bounds.min)
+                .speed(0.01)
+                .range(f64::NEG_INFINITY..=bounds.max),
+        );
+        ui.label("to");
+        ui.add(
+            egui::DragValue::new(&mut bounds.max)
+                .speed(0.01)
+                .range(bounds.min..=f64::INFINITY),
+        );
+        ui.label("kWh");
+    });
+    if !bounds.enabled {
+        return None;
+    }
+
+    let matches = data
+        .entries
+        .iter()
+        .filter(|entry| (bounds.min..=bounds.max).contains(&entry.kilowatt_hours))
+        .collect::<Vec<_>>();
+    let total = matches.iter().map(|entry| entry.kilowatt_hours).sum();
+    ui.label(format!(
+        "{} matches, {} in all",
+        matches.len(),
+        number_format.kilowatt_hours(total)
+    ));
+    results_ui(
+        ui,
+        "usage_search_results",
+        &matches,
+        date_format,
+        time_format,
+        |entry| number_format.kilowatt_hours(entry.kilowatt_hours),
+    )
+}
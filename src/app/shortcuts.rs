@@ -0,0 +1,105 @@
+//! Keyboard shortcuts. [`SHORTCUTS`] is the only list of them, used both to handle key presses
+//! and to build the help window, so the two can't drift apart.
+
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Open,
+    PreviousDay,
+    NextDay,
+    ToggleHelp,
+    ToggleReportMode,
+    CycleTheme,
+}
+
+pub struct Shortcut {
+    pub action: Action,
+    pub shortcut: KeyboardShortcut,
+    pub description: &'static str,
+}
+
+pub const SHORTCUTS: &[Shortcut] = &[
+    Shortcut {
+        action: Action::Open,
+        shortcut: KeyboardShortcut::new(Modifiers::COMMAND, Key::O),
+        description: "Open a usage file",
+    },
+    Shortcut {
+        action: Action::PreviousDay,
+        shortcut: KeyboardShortcut::new(Modifiers::NONE, Key::ArrowLeft),
+        description: "Previous day",
+    },
+    Shortcut {
+        action: Action::NextDay,
+        shortcut: KeyboardShortcut::new(Modifiers::NONE, Key::ArrowRight),
+        description: "Next day",
+    },
+    Shortcut {
+        action: Action::ToggleHelp,
+        shortcut: KeyboardShortcut::new(Modifiers::NONE, Key::Questionmark),
+        description: "Show or hide this list",
+    },
+    Shortcut {
+        action: Action::ToggleReportMode,
+        shortcut: KeyboardShortcut::new(Modifiers::NONE, Key::R),
+        description: "Enter or leave report mode (Esc also leaves it)",
+    },
+    Shortcut {
+        action: Action::CycleTheme,
+        shortcut: KeyboardShortcut::new(Modifiers::COMMAND, Key::L),
+        description: "Switch between light, dark, and system theme",
+    },
+];
+
+/// Consumes the shortcuts pressed this frame and returns their actions.
+///
+/// While a text field has focus only shortcuts with a command modifier are handled, so typing
+/// `?` or moving the cursor with the arrow keys still works.
+pub fn pressed(ctx: &egui::Context) -> Vec<Action> {
+    let typing = ctx.wants_keyboard_input();
+    ctx.input_mut(|input| {
+        SHORTCUTS
+            .iter()
+            .filter(|shortcut| !typing || shortcut.shortcut.modifiers.command)
+            .filter(|shortcut| input.consume_shortcut(&shortcut.shortcut))
+            .map(|shortcut| shortcut.action)
+            .collect()
+    })
+}
+
+/// The theme after the current one, going light, dark, then following the system.
+pub fn next_theme(ctx: &egui::Context) -> egui::ThemePreference {
+    match ctx.options(|options| options.theme_preference) {
+        egui::ThemePreference::Light => egui::ThemePreference::Dark,
+        egui::ThemePreference::Dark => egui::ThemePreference::System,
+        egui::ThemePreference::System => egui::ThemePreference::Light,
+    }
+}
+
+/// The shortcut text to show next to a menu button for `action`.
+pub fn shortcut_text(ctx: &egui::Context, action: Action) -> String {
+    SHORTCUTS
+        .iter()
+        .find(|shortcut| shortcut.action == action)
+        .map(|shortcut| ctx.format_shortcut(&shortcut.shortcut))
+        .unwrap_or_default()
+}
+
+pub fn help_window(ctx: &egui::Context, open: &mut bool) {
+    egui::Window::new("Keyboard shortcuts")
+        .open(open)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("shortcuts_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    for shortcut in SHORTCUTS {
+                        ui.strong(ctx.format_shortcut(&shortcut.shortcut));
+                        ui.label(shortcut.description);
+                        ui.end_row();
+                    }
+                });
+        });
+}
@@ -0,0 +1,280 @@
+//! A rough guess at the appliances behind the usage, from loads that keep turning up at the same
+//! size for the same length of time.
+//!
+//! Each day's lowest draw is taken as its background load. A run of consecutive intervals that
+//! sits well above it is one event, like a dryer running for an hour. Events are then grouped by
+//! their extra draw and length, and groups seen often enough are reported as recurring loads.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::display::NumberFormat;
+use super::{UsageData, UsageEntry};
+
+/// The thresholds the detection is tuned by.
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct SignatureSettings {
+    /// How far above the day's background an interval's draw must be, in kW, to be part of an
+    /// event.
+    pub min_step_kilowatts: f64,
+    /// Events whose extra draw is within this many kW of each other are grouped together.
+    pub power_tolerance: f64,
+    /// Events whose length is within this many hours of each other are grouped together.
+    pub duration_tolerance: f64,
+    /// Groups with fewer events than this aren't reported.
+    pub min_occurrences: usize,
+}
+
+impl Default for SignatureSettings {
+    fn default() -> Self {
+        Self {
+            min_step_kilowatts: 0.5,
+            power_tolerance: 0.5,
+            duration_tolerance: 0.5,
+            min_occurrences: 5,
+        }
+    }
+}
+
+/// A load that keeps coming back at about the same size and length.
+pub struct Signature {
+    /// Average draw above the background, in kW.
+    pub kilowatts: f64,
+    /// Average length of one run, in hours.
+    pub hours: f64,
+    pub occurrences: usize,
+    /// How many different days it ran on.
+    pub days: usize,
+}
+
+/// One stretch of usage above a day's background.
+struct Event {
+    day: NaiveDate,
+    kilowatts: f64,
+    hours: f64,
+}
+
+/// Draw over the entry's interval, in kW.
+fn power(entry: &UsageEntry) -> f64 {
+    entry.kilowatt_hours / entry.duration_hours()
+}
+
+/// Splits each day into events: runs of back-to-back intervals drawing at least `min_step` kW
+/// more than the day's lowest interval.
+fn events(data: &UsageData, min_step: f64) -> Vec<Event> {
+    let mut days: BTreeMap<NaiveDate, Vec<&UsageEntry>> = BTreeMap::new();
+    for entry in data.included() {
+        days.entry(entry.day()).or_default().push(entry);
+    }
+
+    let mut events = Vec::new();
+    for (day, mut entries) in days {
+        entries.sort_by(|a, b| a.start_hours().total_cmp(&b.start_hours()));
+        let background = entries
+            .iter()
+            .map(|entry| power(entry))
+            .fold(f64::MAX, f64::min);
+        // The run being built: where it ends, and its extra energy and length so far.
+        let mut run: Option<(f64, f64, f64)> = None;
+        for entry in entries {
+            let extra = power(entry) - background;
+            let start = entry.start_hours();
+            let hours = entry.duration_hours();
+            // A gap in the data ends a run as surely as a drop in draw does.
+            let continues = run.is_some_and(|(end, _, _)| (end - start).abs() < 1e-6);
+            if extra >= min_step {
+                run = Some(match run.filter(|_| continues) {
+                    Some((_, energy, length)) => {
+                        (start + hours, energy + extra * hours, length + hours)
+                    }
+                    None => {
+                        events.extend(finish(day, run));
+                        (start + hours, extra * hours, hours)
+                    }
+                });
+            } else {
+                events.extend(finish(day, run.take()));
+            }
+        }
+        events.extend(finish(day, run));
+    }
+    events
+}
+
+fn finish(day: NaiveDate, run: Option<(f64, f64, f64)>) -> Option<Event> {
+    let (_, energy, hours) = run?;
+    Some(Event {
+        day,
+        kilowatts: energy / hours,
+        hours,
+    })
+}
+
+/// Recurring loads in `data`, most frequent first.
+pub fn detect(data: &UsageData, settings: &SignatureSettings) -> Vec<Signature> {
+    // Events are binned on a grid as wide as the tolerances, which is rougher than clustering
+    // but needs no tuning of its own.
+    let mut groups: BTreeMap<(i64, i64), Vec<Event>> = BTreeMap::new();
+    let power_step = settings.power_tolerance.max(0.01);
+    let duration_step = settings.duration_tolerance.max(0.01);
+    for event in events(data, settings.min_step_kilowatts) {
+        let key = (
+            (event.kilowatts / power_step).round() as i64,
+            (event.hours / duration_step).round() as i64,
+        );
+        groups.entry(key).or_default().push(event);
+    }
+
+    let mut signatures = groups
+        .into_values()
+        .filter(|events| events.len() >= settings.min_occurrences.max(1))
+        .map(|events| {
+            let count = events.len() as f64;
+            let mut days = events.iter().map(|event| event.day).collect::<Vec<_>>();
+            days.dedup();
+            Signature {
+                kilowatts: events.iter().map(|event| event.kilowatts).sum::<f64>() / count,
+                hours: events.iter().map(|event| event.hours).sum::<f64>() / count,
+                occurrences: events.len(),
+                days: days.len(),
+            }
+        })
+        .collect::<Vec<_>>();
+    signatures.sort_by_key(|signature| std::cmp::Reverse(signature.occurrences));
+    signatures
+}
+
+/// The thresholds, a button to scan with them, and what the last scan found. The scan is only
+/// run on request, since it goes over every entry.
+pub fn signatures_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    settings: &mut SignatureSettings,
+    found: &mut Option<Vec<Signature>>,
+    number_format: NumberFormat,
+) {
+    egui::Grid::new("signature_settings").show(ui, |ui| {
+        ui.label("Above background by");
+        ui.add(
+            egui::DragValue::new(&mut settings.min_step_kilowatts)
+                .speed(0.05)
+                .range(0.05..=20.0)
+                .suffix(" kW"),
+        );
+        ui.end_row();
+        ui.label("Group draws within");
+        ui.add(
+            egui::DragValue::new(&mut settings.power_tolerance)
+                .speed(0.05)
+                .range(0.05..=10.0)
+                .suffix(" kW"),
+        );
+        ui.end_row();
+        ui.label("Group lengths within");
+        ui.add(
+            egui::DragValue::new(&mut settings.duration_tolerance)
+                .speed(0.05)
+                .range(0.05..=12.0)
+                .suffix(" h"),
+        );
+        ui.end_row();
+        ui.label("Seen at least");
+        ui.add(
+            egui::DragValue::new(&mut settings.min_occurrences)
+                .range(1..=1000)
+                .suffix(" times"),
+        );
+        ui.end_row();
+    });
+    if ui.button("Scan for recurring loads").clicked() {
+        *found = Some(detect(data, settings));
+    }
+
+    match found {
+        Some(signatures) if signatures.is_empty() => {
+            ui.weak("Nothing recurs often enough with these settings.");
+        }
+        Some(signatures) => {
+            for signature in signatures.iter() {
+                ui.label(format!(
+                    "~{} kW for ~{:.1} h: {} times on {} days",
+                    number_format.energy(signature.kilowatts),
+                    signature.hours,
+                    signature.occurrences,
+                    signature.days
+                ));
+            }
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::display::TimeFormat;
+
+    /// `days` days in January of hourly readings at a 0.3 kW background, plus `extra(day, hour)`
+    /// kWh on top.
+    fn synthetic(days: u32, extra: impl Fn(u32, u8) -> f64) -> UsageData {
+        let time = |hour| TimeFormat::TwelveHour.format(hour, 0);
+        let mut rows = String::new();
+        for day in 1..=days {
+            for hour in 0..24 {
+                rows.push_str(&format!(
+                    "Electric usage,1/{day}/2024,{},{},{},kWh,\n",
+                    time(hour),
+                    time(hour + 1),
+                    0.3 + extra(day, hour)
+                ));
+            }
+        }
+        UsageData::from_rows(&rows)
+    }
+
+    #[test]
+    fn a_planted_daily_load_is_found() {
+        // A 2 kW load every evening, and a one-off 5 kW one.
+        let data = synthetic(10, |day, hour| match (day, hour) {
+            (_, 18) => 2.0,
+            (3, 10) => 5.0,
+            _ => 0.0,
+        });
+        let signatures = detect(&data, &SignatureSettings::default());
+        assert_eq!(signatures.len(), 1);
+        let signature = &signatures[0];
+        assert!((signature.kilowatts - 2.0).abs() < 1e-9);
+        assert!((signature.hours - 1.0).abs() < 1e-9);
+        assert_eq!((signature.occurrences, signature.days), (10, 10));
+    }
+
+    #[test]
+    fn back_to_back_intervals_are_one_run() {
+        let data = synthetic(
+            6,
+            |_, hour| if (13..15).contains(&hour) { 1.5 } else { 0.0 },
+        );
+        let signatures = detect(&data, &SignatureSettings::default());
+        assert_eq!(signatures.len(), 1);
+        assert!((signatures[0].hours - 2.0).abs() < 1e-9);
+        assert_eq!(signatures[0].occurrences, 6);
+    }
+
+    #[test]
+    fn thresholds_tune_what_is_reported() {
+        let data = synthetic(6, |_, hour| if hour == 18 { 0.4 } else { 0.0 });
+        assert!(detect(&data, &SignatureSettings::default()).is_empty());
+        let sensitive = SignatureSettings {
+            min_step_kilowatts: 0.2,
+            ..SignatureSettings::default()
+        };
+        assert_eq!(detect(&data, &sensitive).len(), 1);
+        let rare = SignatureSettings {
+            min_occurrences: 7,
+            ..sensitive
+        };
+        assert!(detect(&data, &rare).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+//! Writing a day's bar chart as SVG, for figures that stay crisp at any size.
+//!
+//! egui_plot only draws to the screen, so the chart is rebuilt here from the same bars: each bar
+//! becomes a rect, and the axes are plain lines with a few labelled ticks.
+
+use std::fmt::Write as _;
+
+use anyhow::Context;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+/// Bars are placed on a whole day, in hours since midnight.
+const X_RANGE: (f64, f64) = (0.0, 24.0);
+const X_TICK_HOURS: f64 = 3.0;
+const Y_TICKS: usize = 5;
+
+/// Escapes `text` for XML, which HTML accepts as well.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders `bars` (in plot coordinates, as drawn on screen) as an SVG document. `x_tick` and
+/// `y_tick` turn a plot coordinate into its axis label, so non-linear scales label correctly.
+pub fn bar_chart_svg(
+    title: &str,
+    y_label: &str,
+    bars: &[egui_plot::Bar],
+    color: egui::Color32,
+    x_tick: impl Fn(f64) -> String,
+    y_tick: impl Fn(f64) -> String,
+) -> String {
+    let bottom = |bar: &egui_plot::Bar| bar.base_offset.unwrap_or(0.0);
+    let y_min = bars.iter().map(bottom).fold(0.0, f64::min);
+    let mut y_max = bars
+        .iter()
+        .map(|bar| bottom(bar) + bar.value)
+        .fold(y_min, f64::max);
+    if y_max <= y_min {
+        y_max = y_min + 1.0;
+    }
+
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let to_x = |x: f64| MARGIN_LEFT + (x - X_RANGE.0) / (X_RANGE.1 - X_RANGE.0) * plot_width;
+    let to_y = |y: f64| MARGIN_TOP + (y_max - y) / (y_max - y_min) * plot_height;
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    let fill = format!("rgb({r},{g},{b})");
+    let opacity = a as f64 / 255.0;
+
+    let mut svg = String::new();
+    // Writing to a `String` can't fail, so the results are ignored throughout.
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="12">"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" text-anchor="middle" font-size="16">{}</text>"#,
+        WIDTH / 2.0,
+        MARGIN_TOP / 2.0 + 5.0,
+        escape(title)
+    );
+
+    for bar in bars {
+        let half_width = bar.bar_width / 2.0;
+        let left = to_x(bar.argument - half_width);
+        let right = to_x(bar.argument + half_width);
+        let top = to_y(bottom(bar) + bar.value);
+        let base = to_y(bottom(bar));
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{left:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{fill}" fill-opacity="{opacity:.3}"/>"#,
+            top.min(base),
+            right - left,
+            (base - top).abs(),
+        );
+    }
+
+    let axis_y = to_y(y_min);
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{MARGIN_LEFT}" y1="{axis_y:.2}" x2="{:.2}" y2="{axis_y:.2}" stroke="black"/>"#,
+        WIDTH - MARGIN_RIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{MARGIN_LEFT}" y1="{MARGIN_TOP}" x2="{MARGIN_LEFT}" y2="{axis_y:.2}" stroke="black"/>"#
+    );
+
+    let mut hour = X_RANGE.0;
+    while hour <= X_RANGE.1 {
+        let x = to_x(hour);
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x:.2}" y1="{axis_y:.2}" x2="{x:.2}" y2="{:.2}" stroke="black"/>"#,
+            axis_y + 5.0
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{x:.2}" y="{:.2}" text-anchor="middle">{}</text>"#,
+            axis_y + 18.0,
+            escape(&x_tick(hour))
+        );
+        hour += X_TICK_HOURS;
+    }
+
+    for i in 0..=Y_TICKS {
+        let value = y_min + (y_max - y_min) * i as f64 / Y_TICKS as f64;
+        let y = to_y(value);
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{:.2}" y1="{y:.2}" x2="{MARGIN_LEFT}" y2="{y:.2}" stroke="black"/>"#,
+            MARGIN_LEFT - 5.0
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.2}" y="{:.2}" text-anchor="end">{}</text>"#,
+            MARGIN_LEFT - 8.0,
+            y + 4.0,
+            escape(&y_tick(value))
+        );
+    }
+
+    let label_y = MARGIN_TOP + plot_height / 2.0;
+    let _ = writeln!(
+        svg,
+        r#"<text x="15" y="{label_y:.2}" text-anchor="middle" transform="rotate(-90 15 {label_y:.2})">{}</text>"#,
+        escape(y_label)
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Asks where to save, then writes `svg` there.
+pub fn export_svg(svg: &str, file_name: &str) -> anyhow::Result<()> {
+    let Some(path) = rfd::FileDialog::new()
+        .add_filter("SVG image", &["svg"])
+        .set_file_name(file_name)
+        .save_file()
+    else {
+        return Ok(());
+    };
+    std::fs::write(&path, svg).with_context(|| format!("Could not write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chart(title: &str) -> String {
+        let bars = [
+            egui_plot::Bar::new(0.5, 2.0).width(1.0),
+            egui_plot::Bar::new(1.5, 1.0).width(1.0),
+        ];
+        bar_chart_svg(
+            title,
+            "Usage (kWh)",
+            &bars,
+            egui::Color32::from_rgb(10, 20, 30),
+            |hour| format!("{hour}h"),
+            |value| format!("{value:.1}"),
+        )
+    }
+
+    #[test]
+    fn each_bar_is_a_rect_scaled_to_the_plot() {
+        let svg = chart("Usage");
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        // The background, then one rect per bar.
+        assert_eq!(svg.matches("<rect ").count(), 3);
+        // The tallest bar reaches the top margin and fills an hour of the 710-wide plot.
+        assert!(svg.contains(
+            r#"<rect x="70.00" y="40.00" width="29.58" height="320.00" fill="rgb(10,20,30)""#
+        ));
+        assert!(svg.contains(r#"y="200.00" width="29.58" height="160.00""#));
+    }
+
+    #[test]
+    fn labels_and_ticks_are_escaped() {
+        let svg = chart("Mine & <yours>");
+        assert!(svg.contains(">Mine &amp; &lt;yours&gt;</text>"));
+        assert!(svg.contains(">Usage (kWh)</text>"));
+        assert!(svg.contains(">0h</text>") && svg.contains(">24h</text>"));
+        assert!(svg.contains(">2.0</text>"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Tabular listing of usage entries.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::display::{self, DisplaySettings, NumberFormat, TimeFormat};
+use super::UsageEntry;
+
+/// Lists `entries`, each with a box to exclude it, and notes cut to `note_length` characters.
+/// Returns the index of an entry whose box was clicked; the entries are only borrowed, so
+/// toggling it is up to the caller.
+pub fn entries_table_ui(
+    ui: &mut egui::Ui,
+    entries: &[&UsageEntry],
+    number_format: NumberFormat,
+    time_format: TimeFormat,
+    note_length: usize,
+) -> Option<usize> {
+    let mut toggled = None;
+    egui_extras::TableBuilder::new(ui)
+        .striped(true)
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::remainder())
+        .header(20.0, |mut header| {
+            for title in ["Start", "End", "Usage", "Excluded", "Note"] {
+                header.col(|ui| {
+                    ui.strong(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, entries.len(), |mut row| {
+                let row_index = row.index();
+                let entry = entries[row_index];
+                row.col(|ui| {
+                    ui.label(
+                        time_format.format(entry.interval_start_hour, entry.interval_start_minute),
+                    );
+                });
+                row.col(|ui| {
+                    ui.label(
+                        time_format.format(entry.interval_end_hour, entry.interval_end_minute),
+                    );
+                });
+                row.col(|ui| {
+                    ui.label(number_format.kilowatt_hours(entry.kilowatt_hours));
+                });
+                row.col(|ui| {
+                    let mut excluded = entry.excluded;
+                    if ui.checkbox(&mut excluded, "").clicked() {
+                        toggled = Some(row_index);
+                    }
+                });
+                row.col(|ui| {
+                    let note = display::truncate(entry.note(), note_length);
+                    let response = ui.label(note.as_ref());
+                    if note.len() != entry.note().len() {
+                        response.on_hover_text(entry.note());
+                    }
+                });
+            });
+        });
+    toggled
+}
+
+/// Lists `days` one row each, with the day's total and, if `settings` asks for them, a sparkline
+/// of its 24 hours. Returns the day whose date was clicked.
+pub fn days_table_ui(
+    ui: &mut egui::Ui,
+    days: &BTreeMap<NaiveDate, [f64; 24]>,
+    settings: &DisplaySettings,
+) -> Option<NaiveDate> {
+    let days = days.iter().collect::<Vec<_>>();
+    let mut clicked = None;
+    egui_extras::TableBuilder::new(ui)
+        .striped(true)
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::auto())
+        .column(egui_extras::Column::remainder())
+        .header(20.0, |mut header| {
+            for title in ["Day", "Usage", "Hours"] {
+                header.col(|ui| {
+                    ui.strong(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, days.len(), |mut row| {
+                let (&day, hours) = days[row.index()];
+                row.col(|ui| {
+                    if ui.link(settings.date_format.format(day)).clicked() {
+                        clicked = Some(day);
+                    }
+                });
+                row.col(|ui| {
+                    ui.label(
+                        settings
+                            .number_format
+                            .kilowatt_hours(hours.iter().sum::<f64>()),
+                    );
+                });
+                row.col(|ui| {
+                    if settings.show_sparklines {
+                        sparkline_ui(ui, hours, settings.bar_color32());
+                    }
+                });
+            });
+        });
+    clicked
+}
+
+/// A line through `hours`, scaled to fill a small box from its lowest hour (or zero) to its
+/// highest.
+fn sparkline_ui(ui: &mut egui::Ui, hours: &[f64; 24], color: egui::Color32) {
+    let (rect, _response) = ui.allocate_exact_size(egui::vec2(96.0, 14.0), egui::Sense::hover());
+    let low = hours.iter().copied().fold(0.0, f64::min);
+    let high = hours.iter().copied().fold(0.0, f64::max);
+    if high - low <= f64::EPSILON {
+        return;
+    }
+    let points = hours
+        .iter()
+        .enumerate()
+        .map(|(hour, &kilowatt_hours)| {
+            egui::pos2(
+                egui::lerp(rect.x_range(), hour as f32 / 23.0),
+                egui::lerp(
+                    rect.bottom()..=rect.top(),
+                    ((kilowatt_hours - low) / (high - low)) as f32,
+                ),
+            )
+        })
+        .collect();
+    ui.painter()
+        .add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+}
@@ -0,0 +1,119 @@
+//! Tags packed into notes, such as `hvac;ev-charging`, and showing only the entries with some of
+//! them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::display::NumberFormat;
+use super::range::DateRange;
+use super::{DayFilter, UsageData, UsageEntry};
+
+/// Where entries without any tags are counted in [`tag_totals`].
+const UNTAGGED: &str = "untagged";
+
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TagFilter {
+    /// Separates the tags in a note. Left empty, notes aren't split and can't be filtered on.
+    pub delimiter: String,
+    /// Only entries with at least one of these tags are drawn. Empty draws every entry.
+    pub selected: BTreeSet<String>,
+}
+
+impl TagFilter {
+    pub fn includes(&self, entry: &UsageEntry) -> bool {
+        self.delimiter.is_empty()
+            || self.selected.is_empty()
+            || entry
+                .tags(&self.delimiter)
+                .iter()
+                .any(|tag| self.selected.contains(*tag))
+    }
+}
+
+/// Every tag used anywhere in `data`.
+pub fn all_tags<'a>(data: &'a UsageData, delimiter: &'a str) -> BTreeSet<&'a str> {
+    data.entries
+        .iter()
+        .flat_map(|entry| entry.tags(delimiter))
+        .collect()
+}
+
+/// Usage per tag over the days in `range` that pass `filter`. An entry with several tags counts
+/// towards each of them, so the totals can add up to more than the range's usage.
+pub fn tag_totals<'a>(
+    data: &'a UsageData,
+    delimiter: &'a str,
+    range: DateRange,
+    filter: &DayFilter,
+) -> BTreeMap<&'a str, f64> {
+    let mut totals = BTreeMap::new();
+    for entry in data.included() {
+        let day = entry.day();
+        if !(range.start..=range.end).contains(&day) || !filter.includes(day) {
+            continue;
+        }
+        let tags = entry.tags(delimiter);
+        if tags.is_empty() {
+            *totals.entry(UNTAGGED).or_insert(0.0) += entry.kilowatt_hours;
+        }
+        for tag in tags {
+            *totals.entry(tag).or_insert(0.0) += entry.kilowatt_hours;
+        }
+    }
+    totals
+}
+
+/// Each tag's share of `range_total`, largest first.
+pub fn tag_breakdown_ui(
+    ui: &mut egui::Ui,
+    totals: &BTreeMap<&str, f64>,
+    range_total: f64,
+    number_format: NumberFormat,
+) {
+    let mut totals = totals.iter().collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.1.total_cmp(a.1));
+    egui::Grid::new("tag_breakdown").show(ui, |ui| {
+        for (tag, &total) in totals {
+            let share = if range_total > 0.0 {
+                total / range_total
+            } else {
+                0.0
+            };
+            ui.label(*tag);
+            ui.add(
+                egui::ProgressBar::new(share as f32)
+                    .text(format!("{:.1}%", share * 100.0))
+                    .desired_width(150.0),
+            );
+            ui.label(number_format.kilowatt_hours(total));
+            ui.end_row();
+        }
+    });
+}
+
+pub fn tag_filter_ui(ui: &mut egui::Ui, filter: &mut TagFilter, data: &UsageData) {
+    ui.horizontal(|ui| {
+        ui.label("Tag delimiter");
+        ui.add(egui::TextEdit::singleline(&mut filter.delimiter).desired_width(30.0));
+    });
+    if filter.delimiter.is_empty() {
+        return;
+    }
+
+    let tags = all_tags(data, &filter.delimiter);
+    if tags.is_empty() {
+        ui.label("No tags in this file.");
+        return;
+    }
+    ui.label("Show only entries tagged:");
+    for tag in tags {
+        let mut selected = filter.selected.contains(tag);
+        if ui.checkbox(&mut selected, tag).changed() {
+            if selected {
+                filter.selected.insert(tag.to_owned());
+            } else {
+                filter.selected.remove(tag);
+            }
+        }
+    }
+}
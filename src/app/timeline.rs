@@ -0,0 +1,88 @@
+//! Every entry in the range on one continuous time axis, rather than folded into a single day.
+
+use chrono::{Days, NaiveDate};
+
+use super::display::{DateFormat, DisplaySettings, TimeFormat};
+use super::range::DateRange;
+use super::{DayFilter, UsageData};
+
+/// The date and time `x` days after `start`, as the axis and tooltips label it. Whole days are
+/// labelled with just their date.
+fn label(start: NaiveDate, date_format: DateFormat, time_format: TimeFormat, x: f64) -> String {
+    let Ok(days) = u64::try_from(x.floor() as i64) else {
+        return String::new();
+    };
+    let Some(day) = start.checked_add_days(Days::new(days)) else {
+        return String::new();
+    };
+    let hours = (x - x.floor()) * 24.0;
+    if hours.abs() < 1e-6 {
+        date_format.format(day)
+    } else {
+        format!(
+            "{} {}",
+            date_format.format(day),
+            time_format.format_hours(hours)
+        )
+    }
+}
+
+pub fn timeline_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    filter: &DayFilter,
+    range: DateRange,
+    settings: &DisplaySettings,
+) {
+    let unit = settings.unit;
+    let unit_label = unit.label();
+    let number_format = settings.number_format;
+    let time_format = settings.time_format;
+    let date_format = settings.date_format;
+    let start = range.start;
+    let anchor = settings.bar_anchor;
+    // Each entry sits at days since the start of the range, plus the fraction of its day.
+    let bars = data
+        .included()
+        .filter(|entry| {
+            let day = entry.day();
+            (range.start..=range.end).contains(&day) && filter.includes(day)
+        })
+        .map(|entry| {
+            let width = entry.duration_hours() / 24.0;
+            let x = (entry.day() - start).num_days() as f64
+                + entry.start_hours() / 24.0
+                + anchor.offset(width);
+            egui_plot::Bar::new(x, unit.convert(entry.kilowatt_hours)).width(width)
+        })
+        .collect::<Vec<_>>();
+    if bars.is_empty() {
+        ui.label("No usage in the selected range.");
+        return;
+    }
+
+    egui_plot::Plot::new("timeline_plot")
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label(format!("Usage ({unit_label})"))
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| label(start, date_format, time_format, mark.value))
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                egui_plot::BarChart::new("Usage", bars)
+                    .color(settings.bar_color32())
+                    .element_formatter(Box::new(move |bar, _chart| {
+                        format!(
+                            "{}\n{} {unit_label}",
+                            label(
+                                start,
+                                date_format,
+                                time_format,
+                                bar.argument - anchor.offset(bar.bar_width)
+                            ),
+                            number_format.energy(bar.value)
+                        )
+                    })),
+            );
+        });
+}
@@ -0,0 +1,223 @@
+//! Parser for generic two-column `timestamp,kwh` exports, as produced by many smart meters.
+//!
+//! Each row only says when its interval started, so the length of an interval is taken from the
+//! spacing to the next row. Where that spacing can't be right, as at the last row, across a gap in
+//! the data, or over a repeated hour when clocks go back, the file's usual spacing is used instead.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, ensure, Context};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Timelike};
+
+use super::{OnLine, SkippedLine, UsageData, UsageEntry};
+
+const NAIVE_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Whether `input` looks like a two-column file, judging by its first non-empty line. Callers
+/// should rule out the portal format first, since its address line may contain a comma.
+pub fn is_timestamp_csv(input: &str) -> bool {
+    input
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| line.split(',').count() == 2)
+}
+
+pub fn parse_timestamp(timestamp: &str) -> anyhow::Result<NaiveDateTime> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(timestamp) {
+        return Ok(timestamp.naive_local());
+    }
+    NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(timestamp, format).ok())
+        .ok_or_else(|| anyhow!("Invalid timestamp {timestamp:?}, expected ISO 8601"))
+}
+
+/// The most common positive spacing between consecutive rows, which the odd gap or clock change
+/// doesn't throw off. Ties go to the shorter spacing.
+fn usual_spacing(rows: &[(usize, NaiveDateTime, f64)]) -> Option<TimeDelta> {
+    let mut counts = BTreeMap::new();
+    for pair in rows.windows(2) {
+        let spacing = pair[1].1 - pair[0].1;
+        if spacing > TimeDelta::zero() {
+            *counts.entry(spacing).or_insert(0usize) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(spacing, count)| (count, Reverse(spacing)))
+        .map(|(spacing, _)| spacing)
+}
+
+/// Parses the file, leaving out (and reporting) rows that can't become an interval, like one
+/// starting partway through a minute.
+pub fn parse_timestamp_csv(input: &str) -> anyhow::Result<(UsageData, Vec<SkippedLine>)> {
+    let mut rows = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let row = (|| {
+            let Some((timestamp, kilowatt_hours)) = line.split_once(',') else {
+                bail!("Expected two columns");
+            };
+            let Ok(kilowatt_hours) = kilowatt_hours.trim().parse::<f64>() else {
+                // Allow a header row such as `timestamp,kwh`.
+                ensure!(rows.is_empty(), "Invalid usage {kilowatt_hours:?}");
+                return Ok(None);
+            };
+            Ok(Some((
+                i + 1,
+                parse_timestamp(timestamp.trim())?,
+                kilowatt_hours,
+            )))
+        })()
+        .context(OnLine(i + 1))?;
+        rows.extend(row);
+    }
+
+    let usual = usual_spacing(&rows)
+        .context("Need at least two rows at different times to work out the interval length")?;
+    let mut entries = Vec::with_capacity(rows.len());
+    let mut skipped = Vec::new();
+    for (i, &(line, start, kilowatt_hours)) in rows.iter().enumerate() {
+        let spacing = rows
+            .get(i + 1)
+            .map(|&(_, next, _)| next - start)
+            .filter(|&spacing| spacing > TimeDelta::zero() && spacing <= usual)
+            .unwrap_or(usual);
+        // An interval stops at midnight, since entries belong to a single day.
+        let until_midnight =
+            TimeDelta::days(1) - TimeDelta::seconds(start.num_seconds_from_midnight().into());
+        match UsageEntry::from_interval(start, spacing.min(until_midnight), kilowatt_hours) {
+            Ok(entry) => entries.push(entry),
+            Err(error) => skipped.push(SkippedLine {
+                line,
+                message: error.to_string(),
+            }),
+        }
+    }
+
+    let data = UsageData {
+        address: String::new(),
+        meters: Vec::new(),
+        entries,
+    };
+    Ok((data, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An entry's day of the month, start, and end, with times as `(hour, minute)`.
+    type Interval = (u8, (u8, u8), (u8, u8));
+
+    fn intervals(data: &UsageData) -> Vec<Interval> {
+        data.entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.date.2,
+                    (entry.interval_start_hour, entry.interval_start_minute),
+                    (entry.interval_end_hour, entry.interval_end_minute),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn takes_interval_lengths_from_the_spacing() {
+        let (data, skipped) = parse_timestamp_csv(
+            "timestamp,kwh\n2024-01-01T00:00,0.5\n2024-01-01T00:15,0.25\n2024-01-01T00:30,1\n",
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            intervals(&data),
+            [
+                (1, (0, 0), (0, 15)),
+                (1, (0, 15), (0, 30)),
+                (1, (0, 30), (0, 45))
+            ]
+        );
+        assert_eq!(data.entries[2].kilowatt_hours, 1.0);
+    }
+
+    #[test]
+    fn a_gap_keeps_the_usual_spacing() {
+        let (data, skipped) = parse_timestamp_csv(
+            "2024-01-01 10:00,1\n2024-01-01 11:00,1\n2024-01-01 12:00,1\n2024-01-01 18:00,1\n",
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(intervals(&data)[2], (1, (12, 0), (13, 0)));
+        assert_eq!(intervals(&data)[3], (1, (18, 0), (19, 0)));
+    }
+
+    #[test]
+    fn the_last_row_of_a_day_stops_at_midnight() {
+        let (data, skipped) = parse_timestamp_csv(
+            "2024-01-01 22:00,1\n2024-01-01 23:00,1\n2024-01-02 06:00,1\n2024-01-02 07:00,1\n",
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(intervals(&data)[1], (1, (23, 0), (24, 0)));
+
+        let (data, _) = parse_timestamp_csv("2024-01-01 20:00,1\n2024-01-01 23:30,1\n").unwrap();
+        assert_eq!(intervals(&data)[1], (1, (23, 30), (24, 0)));
+    }
+
+    #[test]
+    fn a_repeated_hour_when_clocks_go_back_keeps_every_row() {
+        // The 01:00 hour happens twice on 2024-11-03 in US Pacific time.
+        let (data, skipped) = parse_timestamp_csv(
+            "2024-11-03T00:00:00-07:00,1\n\
+             2024-11-03T01:00:00-07:00,2\n\
+             2024-11-03T01:00:00-08:00,3\n\
+             2024-11-03T02:00:00-08:00,4\n",
+        )
+        .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            intervals(&data),
+            [
+                (3, (0, 0), (1, 0)),
+                (3, (1, 0), (2, 0)),
+                (3, (1, 0), (2, 0)),
+                (3, (2, 0), (3, 0)),
+            ]
+        );
+        let total: f64 = data.entries.iter().map(|entry| entry.kilowatt_hours).sum();
+        assert_eq!(total, 10.0);
+    }
+
+    #[test]
+    fn rows_that_cant_be_intervals_are_reported_not_fatal() {
+        let (data, skipped) = parse_timestamp_csv(
+            "2024-01-01T00:00:00,1\n\
+             2024-01-01T01:00:00,1\n\
+             2024-01-01T02:00:00,1\n\
+             2024-01-01T03:00:30,1\n\
+             2024-01-01T04:00:00,1\n",
+        )
+        .unwrap();
+        assert_eq!(data.entries.len(), 4);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].line, 4);
+    }
+
+    #[test]
+    fn a_bad_value_still_fails_with_its_line() {
+        let Err(error) = parse_timestamp_csv("2024-01-01 00:00,1\n2024-01-01 01:00,lots\n") else {
+            panic!("a row with no number parsed");
+        };
+        assert_eq!(error.downcast_ref::<OnLine>().map(|line| line.0), Some(2));
+    }
+}
@@ -0,0 +1,313 @@
+//! Usage across the selected range, in bars of an adjustable number of hours.
+//!
+//! One slider runs from hourly bars through daily to weekly ones, so the same chart can show
+//! both a single evening's spike and a season's drift. Each bar sums its intervals by default,
+//! or shows their average, largest, or smallest reading.
+
+use std::collections::BTreeMap;
+
+use chrono::Days;
+
+use super::display::DisplaySettings;
+use super::range::DateRange;
+use super::{overview, DayFilter, UsageData};
+
+const MIN_HOURS: u32 = 1;
+const MAX_HOURS: u32 = 24 * 7;
+
+/// What each bar shows of the intervals in its bucket.
+#[derive(Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+pub enum Statistic {
+    #[default]
+    Sum,
+    Average,
+    Max,
+    Min,
+}
+
+impl Statistic {
+    const ALL: [Self; 4] = [Self::Sum, Self::Average, Self::Max, Self::Min];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Sum => "Total",
+            Self::Average => "Average interval",
+            Self::Max => "Largest interval",
+            Self::Min => "Smallest interval",
+        }
+    }
+
+    /// This statistic of one bucket's readings, or `None` for a bucket with none.
+    pub fn of(self, readings: impl IntoIterator<Item = f64>) -> Option<f64> {
+        let mut count = 0;
+        let mut sum = 0.0;
+        let mut max = f64::MIN;
+        let mut min = f64::MAX;
+        for reading in readings {
+            count += 1;
+            sum += reading;
+            max = max.max(reading);
+            min = min.min(reading);
+        }
+        (count > 0).then(|| match self {
+            Self::Sum => sum,
+            Self::Average => sum / count as f64,
+            Self::Max => max,
+            Self::Min => min,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TrendSettings {
+    /// How many hours each bar covers.
+    pub bucket_hours: u32,
+    pub statistic: Statistic,
+}
+
+impl Default for TrendSettings {
+    fn default() -> Self {
+        Self {
+            bucket_hours: 24,
+            statistic: Statistic::default(),
+        }
+    }
+}
+
+/// The bars for one bucket size, statistic, range, and filter. Going over every entry again is
+/// too slow to do each frame on a large dataset, so this is only rebuilt when one of those
+/// changes.
+pub struct Trend {
+    settings: TrendSettings,
+    range: DateRange,
+    filter: DayFilter,
+    /// The statistic of each bucket, keyed by how many buckets it is from the start of the range.
+    values: BTreeMap<i64, f64>,
+}
+
+impl Trend {
+    pub fn new(
+        data: &UsageData,
+        filter: &DayFilter,
+        range: DateRange,
+        settings: TrendSettings,
+    ) -> Self {
+        Self {
+            settings,
+            range,
+            filter: filter.clone(),
+            values: data.aggregate(filter, range, settings.bucket_hours, settings.statistic),
+        }
+    }
+
+    fn is_for(&self, filter: &DayFilter, range: DateRange, settings: TrendSettings) -> bool {
+        self.settings == settings && self.range == range && &self.filter == filter
+    }
+}
+
+/// A bucket size in words, like "6 hours" or "2 days".
+fn describe(hours: u32) -> String {
+    match hours {
+        1 => "1 hour".to_owned(),
+        24 => "1 day".to_owned(),
+        168 => "1 week".to_owned(),
+        hours if hours % 24 == 0 => format!("{} days", hours / 24),
+        hours => format!("{hours} hours"),
+    }
+}
+
+pub fn trend_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    filter: &DayFilter,
+    range: DateRange,
+    settings: &DisplaySettings,
+    trend_settings: &mut TrendSettings,
+    trend: &mut Option<Trend>,
+) {
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::Slider::new(&mut trend_settings.bucket_hours, MIN_HOURS..=MAX_HOURS)
+                .logarithmic(true)
+                .show_value(false),
+        );
+        ui.label(format!("{} per bar", describe(trend_settings.bucket_hours)));
+        egui::ComboBox::from_id_salt("trend_statistic")
+            .selected_text(trend_settings.statistic.label())
+            .show_ui(ui, |ui| {
+                for statistic in Statistic::ALL {
+                    ui.selectable_value(
+                        &mut trend_settings.statistic,
+                        statistic,
+                        statistic.label(),
+                    );
+                }
+            });
+    });
+
+    if trend
+        .as_ref()
+        .map_or(true, |trend| !trend.is_for(filter, range, *trend_settings))
+    {
+        *trend = Some(Trend::new(data, filter, range, *trend_settings));
+    }
+    let Some(trend) = trend else {
+        return;
+    };
+    if trend.values.is_empty() {
+        ui.label("No usage in the selected range.");
+        return;
+    }
+
+    let unit = settings.unit;
+    let unit_label = unit.label();
+    let number_format = settings.number_format;
+    let time_format = settings.time_format;
+    let date_format = settings.date_format;
+    let start = range.start;
+    let bucket_hours = trend.settings.bucket_hours as i64;
+    let statistic = trend.settings.statistic.label();
+    // Bars sit on days since the start of the range, so the axis can label them with dates.
+    let bucket_start = move |hours: i64| {
+        let day = start.checked_add_days(Days::new(u64::try_from(hours / 24).ok()?))?;
+        Some((day, (hours % 24) as u8))
+    };
+    let bars = trend
+        .values
+        .iter()
+        .map(|(&bucket, &value)| {
+            let width = bucket_hours as f64 / 24.0;
+            egui_plot::Bar::new(bucket as f64 * width + width / 2.0, unit.convert(value))
+                .width(width)
+        })
+        .collect::<Vec<_>>();
+    // Only daily sums are the daily totals the labels are for.
+    let labels = if settings.label_daily_totals
+        && bucket_hours == 24
+        && trend.settings.statistic == Statistic::Sum
+    {
+        overview::total_labels(&bars, number_format)
+    } else {
+        Vec::new()
+    };
+
+    egui_plot::Plot::new("trend_plot")
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label(format!("{statistic} ({unit_label})"))
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| {
+            bucket_start((mark.value * 24.0).round() as i64)
+                .map(|(day, _)| date_format.format(day))
+                .unwrap_or_default()
+        })
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(
+                egui_plot::BarChart::new("Usage", bars)
+                    .color(settings.bar_color32())
+                    .element_formatter(Box::new(move |bar, _chart| {
+                        let hours = (bar.argument * 24.0 - bucket_hours as f64 / 2.0).round();
+                        let Some((day, hour)) = bucket_start(hours as i64) else {
+                            return String::new();
+                        };
+                        let when = if bucket_hours % 24 == 0 {
+                            date_format.format(day)
+                        } else {
+                            format!(
+                                "{} {}",
+                                date_format.format(day),
+                                time_format.format(hour, 0)
+                            )
+                        };
+                        format!(
+                            "From {when}\n{statistic}: {} {unit_label}",
+                            number_format.energy(bar.value)
+                        )
+                    })),
+            );
+            for label in labels {
+                plot_ui.text(label);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn each_statistic_of_a_bucket() {
+        let readings = [1.0, 4.0, 2.5];
+        assert_eq!(Statistic::Sum.of(readings), Some(7.5));
+        assert_eq!(Statistic::Average.of(readings), Some(2.5));
+        assert_eq!(Statistic::Max.of(readings), Some(4.0));
+        assert_eq!(Statistic::Min.of(readings), Some(1.0));
+        assert_eq!(Statistic::Min.of([-1.0]), Some(-1.0));
+        assert_eq!(Statistic::Average.of([]), None);
+    }
+
+    #[test]
+    fn readings_are_aggregated_into_buckets_from_the_start_of_the_range() {
+        // Saturday the 13th falls outside the range, and Sunday the 14th is a weekend.
+        let data = UsageData::from_rows(
+            "\
+Electric usage,1/13/2024,1:00 AM,2:00 AM,16.0,kWh,
+Electric usage,1/14/2024,1:00 AM,2:00 AM,32.0,kWh,
+Electric usage,1/15/2024,1:00 AM,2:00 AM,1.0,kWh,
+Electric usage,1/15/2024,2:00 AM,3:00 AM,2.0,kWh,
+Electric usage,1/15/2024,7:00 AM,8:00 AM,4.0,kWh,
+Electric usage,1/16/2024,12:00 AM,1:00 AM,8.0,kWh,
+",
+        );
+        let range = DateRange {
+            start: day(14),
+            end: day(16),
+        };
+        let weekdays = DayFilter {
+            exclude_weekends: true,
+            ..DayFilter::default()
+        };
+        let settings = |statistic| TrendSettings {
+            bucket_hours: 6,
+            statistic,
+        };
+        let values = |statistic| Trend::new(&data, &weekdays, range, settings(statistic)).values;
+        assert_eq!(
+            values(Statistic::Sum),
+            BTreeMap::from([(4, 3.0), (5, 4.0), (8, 8.0)])
+        );
+        assert_eq!(
+            values(Statistic::Average),
+            BTreeMap::from([(4, 1.5), (5, 4.0), (8, 8.0)])
+        );
+        assert_eq!(values(Statistic::Max)[&4], 2.0);
+
+        let daily = Trend::new(
+            &data,
+            &DayFilter::default(),
+            range,
+            TrendSettings::default(),
+        );
+        assert_eq!(
+            daily.values,
+            BTreeMap::from([(0, 32.0), (1, 7.0), (2, 8.0)])
+        );
+        assert!(daily.is_for(&DayFilter::default(), range, TrendSettings::default()));
+        assert!(!daily.is_for(&weekdays, range, TrendSettings::default()));
+    }
+
+    #[test]
+    fn bucket_sizes_in_words() {
+        assert_eq!(describe(1), "1 hour");
+        assert_eq!(describe(6), "6 hours");
+        assert_eq!(describe(24), "1 day");
+        assert_eq!(describe(72), "3 days");
+        assert_eq!(describe(168), "1 week");
+    }
+}
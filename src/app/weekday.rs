@@ -0,0 +1,96 @@
+//! One weekday's hourly profile for every week in the range, to see how a routine day changes.
+
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use super::display::{self, DisplaySettings};
+use super::range::DateRange;
+use super::{DayFilter, UsageData};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Hourly usage for every day in `range` falling on `weekday`, keyed by the Monday starting that
+/// day's week.
+fn weekly_profiles(
+    data: &UsageData,
+    filter: &DayFilter,
+    range: DateRange,
+    weekday: Weekday,
+) -> BTreeMap<NaiveDate, [f64; 24]> {
+    let mut profiles = BTreeMap::new();
+    for entry in data.included() {
+        let day = entry.day();
+        if day.weekday() != weekday || !(range.start..=range.end).contains(&day) {
+            continue;
+        }
+        if !filter.includes(day) {
+            continue;
+        }
+        let week_start = day - Days::new(day.weekday().num_days_from_monday() as u64);
+        let profile = profiles.entry(week_start).or_insert([0.0; 24]);
+        profile[(entry.hour_bucket() as usize).min(23)] += entry.kilowatt_hours;
+    }
+    profiles
+}
+
+pub fn weekday_ui(
+    ui: &mut egui::Ui,
+    data: &UsageData,
+    filter: &DayFilter,
+    range: DateRange,
+    settings: &DisplaySettings,
+    weekday: &mut Weekday,
+) {
+    ui.horizontal(|ui| {
+        for day in WEEKDAYS {
+            ui.selectable_value(weekday, day, day.to_string());
+        }
+    });
+
+    let profiles = weekly_profiles(data, filter, range, *weekday);
+    if profiles.is_empty() {
+        ui.label(format!("No {weekday}s in the selected range."));
+        return;
+    }
+
+    let unit = settings.unit;
+    let time_format = settings.time_format;
+    let number_format = settings.number_format;
+    let date_format = settings.date_format;
+    let unit_label = unit.label();
+    egui_plot::Plot::new("weekday_plot")
+        .legend(display::legend())
+        .show_grid(settings.show_grid)
+        .show_background(!settings.borderless)
+        .y_axis_label(format!("Usage ({unit_label})"))
+        .include_y(0.0)
+        .x_axis_formatter(move |mark, _range| time_format.format_hours(mark.value))
+        .label_formatter(move |name, point| {
+            format!(
+                "{name}\n{}\n{} {unit_label}",
+                time_format.format_hours(point.x),
+                number_format.energy(point.y),
+            )
+        })
+        .show(ui, |plot_ui| {
+            for (week_start, profile) in &profiles {
+                plot_ui.line(egui_plot::Line::new(
+                    format!("Week of {}", date_format.format(*week_start)),
+                    profile
+                        .iter()
+                        .enumerate()
+                        .map(|(hour, &kilowatt_hours)| [hour as f64, unit.convert(kilowatt_hours)])
+                        .collect::<Vec<_>>(),
+                ));
+            }
+        });
+}